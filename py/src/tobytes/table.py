@@ -3,8 +3,11 @@
 Provides serialization support for tabular types including numpy arrays.
 """
 import io
+from dataclasses import dataclass, field
 from typing import TYPE_CHECKING
 
+import msgpack
+
 if TYPE_CHECKING:
     from .codec import Codec
 
@@ -32,6 +35,42 @@ from .codec import NamespaceModule
 table_namespace = NamespaceModule("table")
 
 
+@dataclass
+class RecordBatch:
+    """A column-oriented table of plain msgpack values, for tabular interop
+    without numpy, pandas or polars. All columns must have the same length."""
+    columns: dict[str, list] = field(default_factory=dict)
+
+    def __post_init__(self):
+        self.validate()
+
+    def validate(self):
+        """Raise ValueError if the columns have different lengths."""
+        rows = self.num_rows
+        for name, values in self.columns.items():
+            if len(values) != rows:
+                raise ValueError(
+                    f"Record batch column '{name}' has {len(values)} rows, expected {rows}"
+                )
+
+    @property
+    def num_rows(self) -> int:
+        return len(next(iter(self.columns.values()), []))
+
+
+@table_namespace.encoder(py_type=RecordBatch, type_id=4)
+def encode_record_batch(codec: 'Codec', obj: RecordBatch) -> bytes:
+    """Encode a record batch as a msgpack map of column name to values."""
+    obj.validate()
+    return msgpack.packb(obj.columns, strict_types=True)
+
+
+@encode_record_batch.decoder
+def decode_record_batch(codec: 'Codec', data: bytes) -> RecordBatch:
+    """Decode a record batch from a msgpack map of column name to values."""
+    return RecordBatch(msgpack.unpackb(data, raw=False))
+
+
 if HAS_NUMPY:
     @table_namespace.encoder(py_type=np.ndarray, type_id=1)
     def encode_ndarray(codec: 'Codec', obj: np.ndarray) -> bytes:
@@ -79,4 +118,4 @@ if HAS_POLARS:
         return pl.read_parquet(buf)
 
 
-__all__ = ['table_namespace']
+__all__ = ['table_namespace', 'RecordBatch']
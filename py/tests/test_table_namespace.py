@@ -170,3 +170,27 @@ def test_polars_dataframe_round_trip():
 
     assert isinstance(decoded, pl.DataFrame)
     assert decoded.frame_equal(df)
+
+
+def test_record_batch_round_trip():
+    """Ensure record batches round-trip as a map of columns."""
+    from tobytes.table import RecordBatch
+
+    codec = tobytes.Codec()
+
+    batch = RecordBatch({"id": [1, 2], "name": ["a", None]})
+    assert batch.num_rows == 2
+
+    encoded = codec.dumps(batch)
+    decoded = codec.loads(encoded)
+
+    assert isinstance(decoded, RecordBatch)
+    assert decoded == batch
+
+
+def test_record_batch_rejects_ragged_columns():
+    """Columns of different lengths are rejected."""
+    from tobytes.table import RecordBatch
+
+    with pytest.raises(ValueError):
+        RecordBatch({"id": [1, 2], "name": ["a"]})
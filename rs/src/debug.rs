@@ -0,0 +1,144 @@
+//! Debugging helpers for comparing decoded values.
+
+use std::fmt;
+
+/// A single difference found by [`diff`]. `left` or `right` is `None` when
+/// the path exists on only one side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    pub path: String,
+    pub left: Option<rmpv::Value>,
+    pub right: Option<rmpv::Value>,
+}
+
+impl fmt::Display for DiffEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.left, &self.right) {
+            (Some(left), Some(right)) => write!(f, "{}: {} != {}", self.path, left, right),
+            (Some(left), None) => write!(f, "{}: {} removed", self.path, left),
+            (None, Some(right)) => write!(f, "{}: {} added", self.path, right),
+            (None, None) => write!(f, "{}", self.path),
+        }
+    }
+}
+
+/// Structurally compares two values, descending into arrays and maps, and
+/// returns every path where they differ. The root path is `$`; string map
+/// keys are written `$.key` and other keys and array indices `$[key]`.
+pub fn diff(a: &rmpv::Value, b: &rmpv::Value) -> Vec<DiffEntry> {
+    let mut entries = Vec::new();
+    diff_at("$".to_string(), a, b, &mut entries);
+    entries
+}
+
+fn child_path(path: &str, key: &rmpv::Value) -> String {
+    match key.as_str() {
+        Some(key) => format!("{}.{}", path, key),
+        None => format!("{}[{}]", path, key),
+    }
+}
+
+fn diff_at(path: String, a: &rmpv::Value, b: &rmpv::Value, entries: &mut Vec<DiffEntry>) {
+    match (a, b) {
+        (rmpv::Value::Array(left), rmpv::Value::Array(right)) => {
+            for i in 0..left.len().max(right.len()) {
+                let path = format!("{}[{}]", path, i);
+                match (left.get(i), right.get(i)) {
+                    (Some(l), Some(r)) => diff_at(path, l, r, entries),
+                    (l, r) => entries.push(DiffEntry {
+                        path,
+                        left: l.cloned(),
+                        right: r.cloned(),
+                    }),
+                }
+            }
+        }
+        (rmpv::Value::Map(left), rmpv::Value::Map(right)) => {
+            for (key, l) in left {
+                let path = child_path(&path, key);
+                match right.iter().find(|(k, _)| k == key) {
+                    Some((_, r)) => diff_at(path, l, r, entries),
+                    None => entries.push(DiffEntry {
+                        path,
+                        left: Some(l.clone()),
+                        right: None,
+                    }),
+                }
+            }
+            for (key, r) in right {
+                if !left.iter().any(|(k, _)| k == key) {
+                    entries.push(DiffEntry {
+                        path: child_path(&path, key),
+                        left: None,
+                        right: Some(r.clone()),
+                    });
+                }
+            }
+        }
+        (a, b) if a != b => entries.push(DiffEntry {
+            path,
+            left: Some(a.clone()),
+            right: Some(b.clone()),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmpv::Value;
+    use rstest::rstest;
+
+    fn person(age: i64) -> Value {
+        Value::Map(vec![
+            (Value::from("name"), Value::from("Alice")),
+            (Value::from("age"), Value::from(age)),
+        ])
+    }
+
+    #[rstest]
+    fn test_diff_equal() {
+        assert!(diff(&person(30), &person(30)).is_empty());
+    }
+
+    #[rstest]
+    fn test_diff_changed_value() {
+        let entries = diff(&person(30), &person(31));
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].to_string(), "$.age: 30 != 31");
+    }
+
+    #[rstest]
+    fn test_diff_added_and_removed_keys() {
+        let mut b = person(30);
+        if let Value::Map(pairs) = &mut b {
+            pairs.remove(0);
+            pairs.push((Value::from(1), Value::from(true)));
+        }
+        let entries = diff(&person(30), &b);
+        assert_eq!(
+            entries,
+            vec![
+                DiffEntry {
+                    path: "$.name".to_string(),
+                    left: Some(Value::from("Alice")),
+                    right: None,
+                },
+                DiffEntry {
+                    path: "$[1]".to_string(),
+                    left: None,
+                    right: Some(Value::from(true)),
+                },
+            ]
+        );
+    }
+
+    #[rstest]
+    fn test_diff_type_change() {
+        let a = Value::Array(vec![Value::from(1), person(30)]);
+        let b = Value::Array(vec![Value::from("1"), person(30), Value::Nil]);
+        let entries: Vec<String> = diff(&a, &b).iter().map(|e| e.to_string()).collect();
+        assert_eq!(entries, vec!["$[0]: 1 != \"1\"", "$[2]: nil added"]);
+    }
+}
@@ -1,69 +1,524 @@
+use crate::error::Error;
 use crate::ToBytesResult;
 use rmpv::decode::read_value;
 
 pub trait FromBytes {
     type Output;
 
+    /// The msgpack family this type decodes from, if it accepts only one.
+    /// When set, every `from_*` decoding entry point checks it against the
+    /// first byte before decoding the rest of the value.
+    const EXPECTED: Option<MsgpackFamily> = None;
+
     fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output>;
+
+    /// Decodes the value as of schema `version`. Derived structs default
+    /// fields marked `#[tobytes(since = N)]` when `version < N` or when the
+    /// field is missing, containers pass the version on to their elements,
+    /// and other types ignore it.
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
+        let _ = version;
+        Self::from_value(value)
+    }
+
+    fn from_bytes_versioned<R: std::io::Read>(
+        rd: &mut R,
+        version: u32,
+    ) -> ToBytesResult<Self::Output> {
+        let value = read_expected_value(rd, Self::EXPECTED)?;
+        Self::from_value_versioned(value, version)
+    }
     fn from_bytes<R: std::io::Read>(rd: &mut R) -> ToBytesResult<Self::Output> {
-        let value = read_value(rd)?;
+        let value = read_expected_value(rd, Self::EXPECTED)?;
         Self::from_value(value)
     }
+
+    /// Like `from_bytes`, but wraps `rd` in a `BufReader` first, for readers
+    /// that are slow to read from in small pieces.
+    fn from_reader_buffered<R: std::io::Read>(rd: R) -> ToBytesResult<Self::Output> {
+        Self::from_bytes(&mut std::io::BufReader::new(rd))
+    }
+
+    /// Decodes the first value in `data` if it is complete, returning it
+    /// with the number of bytes consumed, or `Partial::NeedMore` if `data`
+    /// ends partway through it.
+    fn from_partial(data: &[u8]) -> ToBytesResult<Partial<Self::Output>> {
+        if let Some(&marker) = data.first() {
+            check_family(Self::EXPECTED, marker)?;
+        }
+        let mut rd = data;
+        match read_value(&mut rd) {
+            Ok(value) => Ok(Partial::Complete(
+                Self::from_value(value)?,
+                data.len() - rd.len(),
+            )),
+            Err(
+                rmpv::decode::Error::InvalidMarkerRead(err)
+                | rmpv::decode::Error::InvalidDataRead(err),
+            ) if err.kind() == std::io::ErrorKind::UnexpectedEof => Ok(Partial::NeedMore),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Decodes from an owned buffer. The largest binary or string payload in
+    /// the message, whether the whole message or a field inside it, is moved
+    /// to the front of `data` and handed out as that value's `Vec`, rather
+    /// than copied into a new allocation. Other values are copied as usual.
+    fn from_owned_bytes(data: Vec<u8>) -> ToBytesResult<Self::Output> {
+        if let Some(&marker) = data.first() {
+            check_family(Self::EXPECTED, marker)?;
+        }
+        Self::from_value(read_value_reusing(data)?)
+    }
 }
 
-pub fn read_ns_payload<'a, R: std::io::Read>(
-    rd: &'a mut R,
-    expected_namespace: &str,
-    expected_id: i64,
-) -> ToBytesResult<Vec<u8>> {
-    let ext_val = rmpv::decode::read_value(rd)?;
-    if let rmpv::Value::Ext(type_id, data) = ext_val {
-        if type_id != crate::CUSTOM_TYPE_EXT {
-            return Err(crate::error::Error::UnexpectedValue(rmpv::Value::String(
-                format!(
-                    "Expected ext type id '{}', got '{}'",
-                    crate::CUSTOM_TYPE_EXT,
-                    type_id
-                )
-                .into(),
-            )));
+/// Checks a value's first byte against a type's `FromBytes::EXPECTED`
+/// family.
+fn check_family(expected: Option<MsgpackFamily>, marker: u8) -> ToBytesResult<()> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    let found = MsgpackFamily::from_marker(rmp::Marker::from_u8(marker))?;
+    if found != expected {
+        return Err(Error::UnexpectedType { expected, found });
+    }
+    Ok(())
+}
+
+/// Reads a value, checking its first byte against `expected` before reading
+/// the rest of it.
+fn read_expected_value<R: std::io::Read>(
+    rd: &mut R,
+    expected: Option<MsgpackFamily>,
+) -> ToBytesResult<rmpv::Value> {
+    if expected.is_none() {
+        return Ok(read_value(rd)?);
+    }
+    let mut marker = [0u8; 1];
+    rd.read_exact(&mut marker).map_err(Error::from_read)?;
+    check_family(expected, marker[0])?;
+    Ok(read_value(&mut std::io::Read::chain(&marker[..], rd))?)
+}
+
+/// Reads the first value in `data`, reusing `data`'s allocation for its
+/// largest non-empty binary or valid UTF-8 string payload.
+fn read_value_reusing(mut data: Vec<u8>) -> ToBytesResult<rmpv::Value> {
+    let (mut value, reused) = {
+        let value_ref = rmpv::decode::read_value_ref(&mut &data[..])?;
+        let largest = largest_payload(&value_ref);
+        let mut slot = None;
+        let value = owned_except(
+            &value_ref,
+            largest.map(|(payload, _)| payload),
+            &mut Vec::new(),
+            &mut slot,
+        );
+        let reused = largest.zip(slot).map(|((payload, is_str), path)| {
+            let offset = payload.as_ptr() as usize - data.as_ptr() as usize;
+            (offset, payload.len(), is_str, path)
+        });
+        (value, reused)
+    };
+
+    if let Some((offset, len, is_str, path)) = reused {
+        data.truncate(offset + len);
+        data.drain(..offset);
+        let slot = path
+            .into_iter()
+            .fold(&mut value, |value, index| match value {
+                rmpv::Value::Array(items) => &mut items[index],
+                rmpv::Value::Map(pairs) => {
+                    let (key, val) = &mut pairs[index / 2];
+                    if index % 2 == 0 {
+                        key
+                    } else {
+                        val
+                    }
+                }
+                _ => unreachable!("slot paths only pass through arrays and maps"),
+            });
+        *slot = if is_str {
+            // Validated as UTF-8 when it was picked.
+            rmpv::Value::String(String::from_utf8(data).expect("valid UTF-8").into())
+        } else {
+            rmpv::Value::Binary(data)
+        };
+    }
+    Ok(value)
+}
+
+/// The longest non-empty binary or valid UTF-8 string payload in `value`,
+/// and whether it is a string.
+fn largest_payload<'a>(value: &rmpv::ValueRef<'a>) -> Option<(&'a [u8], bool)> {
+    let children: Box<dyn Iterator<Item = &rmpv::ValueRef<'a>>> = match value {
+        rmpv::ValueRef::Binary(bytes) if !bytes.is_empty() => return Some((bytes, false)),
+        rmpv::ValueRef::String(s) => {
+            return s
+                .into_str()
+                .filter(|s| !s.is_empty())
+                .map(|s| (s.as_bytes(), true))
         }
-        let mut cursor = std::io::Cursor::new(data);
-        let ns_name_utf_raw: rmpv::Utf8String =
-            rmpv::decode::read_value(&mut cursor)?.try_into()?;
-        let ns_name: &str = ns_name_utf_raw.as_str().ok_or_else(|| {
-            crate::error::Error::UnexpectedValue(rmpv::Value::String(
-                "Namespace name is not valid UTF-8".into(),
-            ))
-        })?;
-        if ns_name != expected_namespace {
-            return Err(crate::error::Error::UnexpectedValue(rmpv::Value::String(
-                format!(
-                    "Expected namespace '{}', got '{}'",
-                    expected_namespace, ns_name
-                )
-                .into(),
-            )));
+        rmpv::ValueRef::Array(items) => Box::new(items.iter()),
+        rmpv::ValueRef::Map(pairs) => Box::new(pairs.iter().flat_map(|(k, v)| [k, v])),
+        _ => return None,
+    };
+    children
+        .filter_map(largest_payload)
+        .fold(None, |best: Option<(&[u8], bool)>, candidate| match best {
+            Some(best) if best.0.len() >= candidate.0.len() => Some(best),
+            _ => Some(candidate),
+        })
+}
+
+/// Converts `value` to an owned value, leaving a nil placeholder for the
+/// payload starting at `skip` and recording the path to it in `slot`. Map
+/// entries count as two children each: key then value.
+fn owned_except(
+    value: &rmpv::ValueRef,
+    skip: Option<&[u8]>,
+    path: &mut Vec<usize>,
+    slot: &mut Option<Vec<usize>>,
+) -> rmpv::Value {
+    let is_skipped = |payload: &[u8]| {
+        skip.is_some_and(|skip| std::ptr::eq(skip.as_ptr(), payload.as_ptr()))
+            && !payload.is_empty()
+    };
+    let mut child = |index: usize, item: &rmpv::ValueRef, slot: &mut Option<Vec<usize>>| {
+        path.push(index);
+        let owned = owned_except(item, skip, path, slot);
+        path.pop();
+        owned
+    };
+    match value {
+        rmpv::ValueRef::Binary(bytes) if is_skipped(bytes) => {
+            *slot = Some(path.clone());
+            rmpv::Value::Nil
         }
-        let value_id: u64 = rmpv::decode::read_value(&mut cursor)?.try_into()?;
-        if value_id != expected_id as u64 {
-            return Err(crate::error::Error::UnexpectedValue(rmpv::Value::String(
-                format!("Expected id '{}', got '{}'", expected_id, value_id).into(),
-            )));
+        rmpv::ValueRef::String(s) if s.into_str().is_some_and(|s| is_skipped(s.as_bytes())) => {
+            *slot = Some(path.clone());
+            rmpv::Value::Nil
+        }
+        rmpv::ValueRef::Array(items) => rmpv::Value::Array(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| child(i, item, slot))
+                .collect(),
+        ),
+        rmpv::ValueRef::Map(pairs) => rmpv::Value::Map(
+            pairs
+                .iter()
+                .enumerate()
+                .map(|(i, (key, val))| (child(2 * i, key, slot), child(2 * i + 1, val, slot)))
+                .collect(),
+        ),
+        other => other.to_owned(),
+    }
+}
+
+/// Decodes a value from a hex string as produced by `to_hex`.
+pub fn from_hex<T: FromBytes>(hex: &str) -> ToBytesResult<T::Output> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(Error::InvalidHex(format!("odd length {}", hex.len())));
+    }
+    let bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                // `from_str_radix` also accepts a leading sign.
+                .filter(|pair| pair.bytes().all(|b| b.is_ascii_hexdigit()))
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .ok_or_else(|| Error::InvalidHex(format!("invalid digits at offset {}", i)))
+        })
+        .collect::<ToBytesResult<Vec<u8>>>()?;
+    T::from_bytes(&mut &bytes[..])
+}
+
+/// Result of [`FromBytes::from_partial`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Partial<T> {
+    /// A decoded value and the number of bytes it used.
+    Complete(T, usize),
+    NeedMore,
+}
+
+/// The format family of a msgpack value, as reported by [`peek_type`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MsgpackType {
+    Nil,
+    Bool,
+    Int,
+    Float,
+    Str,
+    Bin,
+    Array,
+    Map,
+    Ext(i8),
+}
+
+impl MsgpackType {
+    pub fn family(self) -> MsgpackFamily {
+        match self {
+            MsgpackType::Nil => MsgpackFamily::Nil,
+            MsgpackType::Bool => MsgpackFamily::Bool,
+            MsgpackType::Int => MsgpackFamily::Int,
+            MsgpackType::Float => MsgpackFamily::Float,
+            MsgpackType::Str => MsgpackFamily::Str,
+            MsgpackType::Bin => MsgpackFamily::Bin,
+            MsgpackType::Array => MsgpackFamily::Array,
+            MsgpackType::Map => MsgpackFamily::Map,
+            MsgpackType::Ext(_) => MsgpackFamily::Ext,
+        }
+    }
+}
+
+/// A msgpack format family, determined by a value's first byte alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MsgpackFamily {
+    Nil,
+    Bool,
+    Int,
+    Float,
+    Str,
+    Bin,
+    Array,
+    Map,
+    Ext,
+}
+
+impl MsgpackFamily {
+    pub fn from_marker(marker: rmp::Marker) -> ToBytesResult<Self> {
+        use rmp::Marker;
+
+        Ok(match marker {
+            Marker::Null => MsgpackFamily::Nil,
+            Marker::True | Marker::False => MsgpackFamily::Bool,
+            Marker::FixPos(_)
+            | Marker::FixNeg(_)
+            | Marker::U8
+            | Marker::U16
+            | Marker::U32
+            | Marker::U64
+            | Marker::I8
+            | Marker::I16
+            | Marker::I32
+            | Marker::I64 => MsgpackFamily::Int,
+            Marker::F32 | Marker::F64 => MsgpackFamily::Float,
+            Marker::FixStr(_) | Marker::Str8 | Marker::Str16 | Marker::Str32 => MsgpackFamily::Str,
+            Marker::Bin8 | Marker::Bin16 | Marker::Bin32 => MsgpackFamily::Bin,
+            Marker::FixArray(_) | Marker::Array16 | Marker::Array32 => MsgpackFamily::Array,
+            Marker::FixMap(_) | Marker::Map16 | Marker::Map32 => MsgpackFamily::Map,
+            Marker::FixExt1
+            | Marker::FixExt2
+            | Marker::FixExt4
+            | Marker::FixExt8
+            | Marker::FixExt16
+            | Marker::Ext8
+            | Marker::Ext16
+            | Marker::Ext32 => MsgpackFamily::Ext,
+            Marker::Reserved => return Err(Error::ReservedMarker(marker.to_u8())),
+        })
+    }
+}
+
+/// Reports the type of the first msgpack value in `data` from its marker
+/// (and, for ext values, the ext header) without decoding the body.
+pub fn peek_type(data: &[u8]) -> ToBytesResult<MsgpackType> {
+    let marker = rmp::decode::read_marker(&mut &data[..]).map_err(|err| Error::from_read(err.0))?;
+    Ok(match MsgpackFamily::from_marker(marker)? {
+        MsgpackFamily::Nil => MsgpackType::Nil,
+        MsgpackFamily::Bool => MsgpackType::Bool,
+        MsgpackFamily::Int => MsgpackType::Int,
+        MsgpackFamily::Float => MsgpackType::Float,
+        MsgpackFamily::Str => MsgpackType::Str,
+        MsgpackFamily::Bin => MsgpackType::Bin,
+        MsgpackFamily::Array => MsgpackType::Array,
+        MsgpackFamily::Map => MsgpackType::Map,
+        MsgpackFamily::Ext => MsgpackType::Ext(rmp::decode::read_ext_meta(&mut &data[..])?.typeid),
+    })
+}
+
+/// How a custom type names its namespace: by string, or by an integer id
+/// assigned with a custom type namespace id mapping.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NamespaceRef {
+    Name(String),
+    Id(u32),
+}
+
+impl NamespaceRef {
+    pub fn name(name: impl Into<String>) -> Self {
+        NamespaceRef::Name(name.into())
+    }
+
+    pub fn id(id: u32) -> Self {
+        NamespaceRef::Id(id)
+    }
+}
+
+impl std::fmt::Display for NamespaceRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NamespaceRef::Name(name) => write!(f, "{}", name),
+            NamespaceRef::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+impl From<&str> for NamespaceRef {
+    fn from(name: &str) -> Self {
+        NamespaceRef::Name(name.to_string())
+    }
+}
+
+impl From<String> for NamespaceRef {
+    fn from(name: String) -> Self {
+        NamespaceRef::Name(name)
+    }
+}
+
+impl From<u32> for NamespaceRef {
+    fn from(id: u32) -> Self {
+        NamespaceRef::Id(id)
+    }
+}
+
+/// Longest namespace name accepted when decoding a custom type header.
+pub const MAX_NAMESPACE_LEN: u32 = 256;
+
+/// Reads a namespace name or id, checking the name length before reading
+/// the name itself.
+fn read_namespace<R: std::io::Read>(rd: &mut R) -> ToBytesResult<NamespaceRef> {
+    use std::io::Read;
+
+    let mut marker = [0u8; 1];
+    rd.read_exact(&mut marker).map_err(Error::from_read)?;
+    let mut rd = (&marker[..]).chain(rd);
+    match rmp::Marker::from_u8(marker[0]) {
+        rmp::Marker::FixStr(_) | rmp::Marker::Str8 | rmp::Marker::Str16 | rmp::Marker::Str32 => {
+            let len = rmp::decode::read_str_len(&mut rd)?;
+            if len > MAX_NAMESPACE_LEN {
+                return Err(Error::NamespaceTooLong(len));
+            }
+            let mut name = vec![0; len as usize];
+            rd.read_exact(&mut name).map_err(Error::from_read)?;
+            String::from_utf8(name)
+                .map(NamespaceRef::Name)
+                .map_err(Error::InvalidNamespaceName)
         }
-        let pos = cursor.position() as usize;
-        Ok(cursor.into_inner()[pos..].to_vec())
-    } else {
-        Err(crate::error::Error::UnexpectedValue(rmpv::Value::String(
-            "Expected ext value".into(),
-        )))
+        _ => match read_value(&mut rd)? {
+            rmpv::Value::Integer(id) => Ok(NamespaceRef::Id(
+                id.as_u64()
+                    .and_then(|id| u32::try_from(id).ok())
+                    .ok_or(rmpv::Value::Integer(id))?,
+            )),
+            other => Err(other.into()),
+        },
+    }
+}
+
+/// The header of a custom type ext value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NsHeader {
+    pub namespace: NamespaceRef,
+    pub type_id: u32,
+    /// Number of payload bytes following the header.
+    pub payload_len: usize,
+}
+
+/// Reads the ext header, namespace and type id of a custom type, leaving
+/// `rd` positioned at the start of its `payload_len` byte payload.
+pub fn peek_ns_header<R: std::io::Read>(rd: &mut R) -> ToBytesResult<NsHeader> {
+    let meta = rmp::decode::read_ext_meta(rd)?;
+    if meta.typeid != crate::CUSTOM_TYPE_EXT {
+        return Err(Error::WrongExtType {
+            expected: crate::CUSTOM_TYPE_EXT,
+            found: meta.typeid,
+        });
+    }
+    let mut header = std::io::Read::take(rd, meta.size as u64);
+    if header.limit() == 0 {
+        return Err(Error::TruncatedCustomType("namespace"));
+    }
+    let namespace = read_namespace(&mut header)?;
+    if header.limit() == 0 {
+        return Err(Error::TruncatedCustomType("type id"));
     }
+    let type_id = match read_value(&mut header)? {
+        rmpv::Value::Integer(id) => id
+            .as_u64()
+            .and_then(|id| u32::try_from(id).ok())
+            .ok_or(rmpv::Value::Integer(id))?,
+        other => return Err(other.into()),
+    };
+    Ok(NsHeader {
+        namespace,
+        type_id,
+        payload_len: header.limit() as usize,
+    })
+}
+
+pub fn read_ns_payload<R: std::io::Read>(
+    rd: &mut R,
+    expected_namespace: &str,
+    expected_id: i64,
+) -> ToBytesResult<Vec<u8>> {
+    read_ns_payload_ref(rd, &NamespaceRef::name(expected_namespace), expected_id)
+}
+
+/// Like [`read_ns_payload`], for custom types whose namespace is written as
+/// an integer id rather than a name.
+pub fn read_ns_payload_by_id<R: std::io::Read>(
+    rd: &mut R,
+    expected_namespace_id: u32,
+    expected_id: i64,
+) -> ToBytesResult<Vec<u8>> {
+    read_ns_payload_ref(rd, &NamespaceRef::id(expected_namespace_id), expected_id)
+}
+
+fn read_ns_payload_ref<R: std::io::Read>(
+    rd: &mut R,
+    expected_namespace: &NamespaceRef,
+    expected_id: i64,
+) -> ToBytesResult<Vec<u8>> {
+    let header = peek_ns_header(rd)?;
+    if &header.namespace != expected_namespace {
+        return Err(Error::WrongNamespace {
+            expected: expected_namespace.to_string(),
+            found: header.namespace.to_string(),
+        });
+    }
+    if i64::from(header.type_id) != expected_id {
+        return Err(Error::WrongId {
+            expected: expected_id,
+            found: header.type_id.into(),
+        });
+    }
+    read_payload(rd, &header)
+}
+
+/// Reads the payload following a custom type header.
+pub(crate) fn read_payload<R: std::io::Read>(
+    rd: &mut R,
+    header: &NsHeader,
+) -> ToBytesResult<Vec<u8>> {
+    use std::io::Read;
+
+    // Grows with the bytes actually read rather than trusting the header.
+    let mut payload = Vec::new();
+    rd.take(header.payload_len as u64)
+        .read_to_end(&mut payload)?;
+    if payload.len() != header.payload_len {
+        return Err(Error::UnexpectedEof);
+    }
+    Ok(payload)
 }
 
 macro_rules! impl_primitive_decode {
-    ($t:ty, $inter:ty) => {
+    ($t:ty, $inter:ty, $family:ident) => {
         impl FromBytes for $t {
             type Output = $t;
+            const EXPECTED: Option<MsgpackFamily> = Some(MsgpackFamily::$family);
             fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
                 let inter: $inter = <$inter>::try_from(value)?;
                 Ok(inter as Self::Output)
@@ -72,21 +527,122 @@ macro_rules! impl_primitive_decode {
     };
 }
 
-impl_primitive_decode!(bool, bool);
-impl_primitive_decode!(u8, u64);
-impl_primitive_decode!(u16, u64);
-impl_primitive_decode!(u32, u64);
-impl_primitive_decode!(u64, u64);
-impl_primitive_decode!(usize, u64);
-impl_primitive_decode!(i8, i64);
-impl_primitive_decode!(i16, i64);
-impl_primitive_decode!(i32, i64);
-impl_primitive_decode!(i64, i64);
-impl_primitive_decode!(isize, i64);
+impl_primitive_decode!(bool, bool, Bool);
+impl_primitive_decode!(u8, u64, Int);
+impl_primitive_decode!(u16, u64, Int);
+impl_primitive_decode!(u32, u64, Int);
+impl_primitive_decode!(u64, u64, Int);
+impl_primitive_decode!(usize, u64, Int);
+impl_primitive_decode!(i8, i64, Int);
+impl_primitive_decode!(i16, i64, Int);
+impl_primitive_decode!(i32, i64, Int);
+impl_primitive_decode!(i64, i64, Int);
+impl_primitive_decode!(isize, i64, Int);
+
+/// Floats also accept msgpack integers, as JSON-origin data often writes
+/// `1` for `1.0`. Integers beyond 2^53 (2^24 for `f32`) lose precision.
+macro_rules! impl_float_decode {
+    ($t:ty) => {
+        impl FromBytes for $t {
+            type Output = $t;
+            fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+                match value {
+                    rmpv::Value::Integer(i) => match (i.as_i64(), i.as_u64()) {
+                        (Some(i), _) => Ok(i as $t),
+                        (_, Some(u)) => Ok(u as $t),
+                        _ => Err(rmpv::Value::Integer(i).into()),
+                    },
+                    other => Ok(<$t>::try_from(other)?),
+                }
+            }
+        }
+    };
+}
+
+impl_float_decode!(f32);
+impl_float_decode!(f64);
+impl_primitive_decode!(String, String, Str);
+
+impl<'a> FromBytes for std::borrow::Cow<'a, str> {
+    type Output = std::borrow::Cow<'a, str>;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Ok(std::borrow::Cow::Owned(String::try_from(value)?))
+    }
+}
+
+impl<T: FromBytes<Output = T>> FromBytes for std::num::Wrapping<T> {
+    type Output = std::num::Wrapping<T>;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Self::from_value_versioned(value, u32::MAX)
+    }
+
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
+        Ok(std::num::Wrapping(T::from_value_versioned(value, version)?))
+    }
+}
+
+impl<T: FromBytes<Output = T>> FromBytes for Option<T> {
+    type Output = Option<T>;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Self::from_value_versioned(value, u32::MAX)
+    }
+
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
+        match value {
+            rmpv::Value::Nil => Ok(None),
+            value => Ok(Some(T::from_value_versioned(value, version)?)),
+        }
+    }
+}
+
+/// Decodes any of the 32, 64 or 96-bit msgpack timestamp forms, selected
+/// by the ext payload length.
+impl FromBytes for std::time::SystemTime {
+    type Output = std::time::SystemTime;
 
-impl_primitive_decode!(f32, f32);
-impl_primitive_decode!(f64, f64);
-impl_primitive_decode!(String, String);
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        let data = match value {
+            rmpv::Value::Ext(crate::TIMESTAMP_EXT, data) => data,
+            other => return Err(other.into()),
+        };
+        let (secs, nanos) = match data.len() {
+            4 => (u32::from_be_bytes(data[..].try_into().unwrap()) as i64, 0),
+            8 => {
+                let data64 = u64::from_be_bytes(data[..].try_into().unwrap());
+                ((data64 & 0x3_ffff_ffff) as i64, (data64 >> 34) as u32)
+            }
+            12 => (
+                i64::from_be_bytes(data[4..].try_into().unwrap()),
+                u32::from_be_bytes(data[..4].try_into().unwrap()),
+            ),
+            len => {
+                return Err(Error::InvalidTimestamp(format!(
+                    "expected a 4, 8 or 12 byte payload, got {} bytes",
+                    len
+                )))
+            }
+        };
+        if nanos >= 1_000_000_000 {
+            return Err(Error::InvalidTimestamp(format!(
+                "nanoseconds out of range: {}",
+                nanos
+            )));
+        }
+        let epoch = std::time::UNIX_EPOCH;
+        let time = if secs >= 0 {
+            epoch.checked_add(std::time::Duration::from_secs(secs as u64))
+        } else {
+            epoch.checked_sub(std::time::Duration::from_secs(secs.unsigned_abs()))
+        };
+        time.and_then(|t| t.checked_add(std::time::Duration::from_nanos(nanos as u64)))
+            .ok_or_else(|| {
+                Error::InvalidTimestamp(format!("{}s {}ns is out of range", secs, nanos))
+            })
+    }
+}
 
 #[derive(Debug, PartialEq)]
 pub struct Bytes(pub Vec<u8>);
@@ -100,6 +656,70 @@ impl FromBytes for Bytes {
     }
 }
 
+impl FromBytes for rmpv::Value {
+    type Output = rmpv::Value;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Ok(value)
+    }
+}
+
+impl<const N: usize> FromBytes for [u8; N] {
+    type Output = [u8; N];
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        let data = match value {
+            rmpv::Value::Binary(data) => data,
+            other => return Err(other.into()),
+        };
+        <[u8; N]>::try_from(data).map_err(|data| Error::LengthMismatch {
+            expected: N,
+            found: data.len(),
+        })
+    }
+}
+
+impl FromBytes for std::net::IpAddr {
+    type Output = std::net::IpAddr;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        match value {
+            rmpv::Value::Binary(data) if data.len() == 4 => {
+                Ok(std::net::Ipv4Addr::from_value(rmpv::Value::Binary(data))?.into())
+            }
+            rmpv::Value::Binary(data) if data.len() == 16 => {
+                Ok(std::net::Ipv6Addr::from_value(rmpv::Value::Binary(data))?.into())
+            }
+            other => Err(other.into()),
+        }
+    }
+}
+
+impl FromBytes for std::net::Ipv4Addr {
+    type Output = std::net::Ipv4Addr;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Ok(<[u8; 4]>::from_value(value)?.into())
+    }
+}
+
+impl FromBytes for std::net::Ipv6Addr {
+    type Output = std::net::Ipv6Addr;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Ok(<[u8; 16]>::from_value(value)?.into())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl FromBytes for uuid::Uuid {
+    type Output = uuid::Uuid;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Ok(uuid::Uuid::from_bytes(<[u8; 16]>::from_value(value)?))
+    }
+}
+
 impl<T> FromBytes for Vec<T>
 where
     T: FromBytes<Output = T>,
@@ -107,12 +727,45 @@ where
     type Output = Vec<T>;
 
     fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Self::from_value_versioned(value, u32::MAX)
+    }
+
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
         let vec = Vec::<rmpv::Value>::try_from(value)?;
 
-        Ok(vec
-            .into_iter()
-            .map(|item| T::from_value(item))
-            .collect::<ToBytesResult<Vec<T>>>()?)
+        vec.into_iter()
+            .map(|item| T::from_value_versioned(item, version))
+            .collect::<ToBytesResult<Vec<T>>>()
+    }
+}
+
+impl<T> FromBytes for std::collections::VecDeque<T>
+where
+    T: FromBytes<Output = T>,
+{
+    type Output = std::collections::VecDeque<T>;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Self::from_value_versioned(value, u32::MAX)
+    }
+
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
+        Ok(Vec::<T>::from_value_versioned(value, version)?.into())
+    }
+}
+
+impl<T> FromBytes for std::collections::BinaryHeap<T>
+where
+    T: FromBytes<Output = T> + Ord,
+{
+    type Output = std::collections::BinaryHeap<T>;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Self::from_value_versioned(value, u32::MAX)
+    }
+
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
+        Ok(Vec::<T>::from_value_versioned(value, version)?.into())
     }
 }
 
@@ -124,12 +777,41 @@ where
     type Output = std::collections::HashMap<T, U>;
 
     fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Self::from_value_versioned(value, u32::MAX)
+    }
+
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
         let values = Vec::<(rmpv::Value, rmpv::Value)>::try_from(value)?;
 
         let mut result = std::collections::HashMap::new();
         for (key, val) in values.into_iter() {
-            let k = T::from_value(key)?;
-            let v = U::from_value(val)?;
+            let k = T::from_value_versioned(key, version)?;
+            let v = U::from_value_versioned(val, version)?;
+            result.insert(k, v);
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(feature = "indexmap")]
+impl<T, U> FromBytes for indexmap::IndexMap<T, U>
+where
+    T: FromBytes<Output = T> + std::hash::Hash + Eq,
+    U: FromBytes<Output = U>,
+{
+    type Output = indexmap::IndexMap<T, U>;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Self::from_value_versioned(value, u32::MAX)
+    }
+
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
+        let values = Vec::<(rmpv::Value, rmpv::Value)>::try_from(value)?;
+
+        let mut result = indexmap::IndexMap::with_capacity(values.len());
+        for (key, val) in values.into_iter() {
+            let k = T::from_value_versioned(key, version)?;
+            let v = U::from_value_versioned(val, version)?;
             result.insert(k, v);
         }
         Ok(result)
@@ -137,6 +819,7 @@ where
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use super::*;
     use compose_idents::compose;
@@ -200,39 +883,715 @@ mod tests {
     core_type_value!(Vec<u8>, vec![1u8, 2u8, 3u8], vec![0x93, 0x01, 0x02, 0x03]);
 
     #[rstest]
-    fn test_decoding_hashmap() {
-        let value1: Vec<u8> = vec![0b10000010, 0x01, 0x02, 0x03, 0x04]; // {1: 2, 3: 4}
-        let value2: Vec<u8> = vec![0b10000010, 0x03, 0x04, 0x01, 0x02]; // {3: 4, 1: 2}
+    fn test_from_owned_bytes_reuses_binary_buffer() {
+        let mut data = vec![0xc6, 0x00, 0x01, 0x00, 0x00];
+        data.extend(std::iter::repeat_n(0xab, 65536));
+        let ptr = data.as_ptr();
 
-        let expected = {
-            let mut map = std::collections::HashMap::new();
-            map.insert(1, 2);
-            map.insert(3, 4);
-            map
-        };
+        let Bytes(decoded) = Bytes::from_owned_bytes(data).unwrap();
+        assert_eq!(decoded, vec![0xab; 65536]);
+        assert_eq!(decoded.as_ptr(), ptr);
+    }
 
-        let actual1 = std::collections::HashMap::<u8, u8>::from_bytes(&mut &value1[..]).unwrap();
-        let actual2 = std::collections::HashMap::<u8, u8>::from_bytes(&mut &value2[..]).unwrap();
+    #[rstest]
+    fn test_from_owned_bytes_non_binary() {
+        let data = vec![0x93, 0x01, 0x02, 0x03];
+        let decoded = Vec::<u8>::from_owned_bytes(data).unwrap();
+        assert_eq!(decoded, vec![1, 2, 3]);
+    }
 
-        assert!(
-            actual1 == expected,
-            "Expected: {:?}, Actual: {:?}",
-            expected,
-            actual1
+    #[rstest]
+    fn test_namespace_ref_constructors() {
+        assert_eq!(
+            NamespaceRef::name("table"),
+            NamespaceRef::Name("table".to_string())
         );
-        assert!(
-            actual2 == expected,
-            "Expected: {:?}, Actual: {:?}",
+        assert_eq!(NamespaceRef::id(3), NamespaceRef::Id(3));
+        assert_eq!(
+            NamespaceRef::from("table"),
+            NamespaceRef::Name("table".to_string())
+        );
+        assert_eq!(
+            NamespaceRef::from("table".to_string()),
+            NamespaceRef::name("table")
+        );
+        assert_eq!(NamespaceRef::from(3u32), NamespaceRef::Id(3));
+    }
+
+    #[rstest]
+    fn test_read_ns_payload_truncated() {
+        let mut data = vec![];
+        rmp::encode::write_str(&mut data, "table").unwrap();
+        let mut buf = vec![];
+        rmp::encode::write_ext_meta(&mut buf, data.len() as u32, crate::CUSTOM_TYPE_EXT).unwrap();
+        buf.extend_from_slice(&data);
+
+        let err = read_ns_payload(&mut buf.as_slice(), "table", 1).unwrap_err();
+        assert!(matches!(err, Error::TruncatedCustomType("type id")));
+        let err = peek_ns_header(&mut buf.as_slice()).unwrap_err();
+        assert!(matches!(err, Error::TruncatedCustomType("type id")));
+
+        let mut empty = vec![];
+        rmp::encode::write_ext_meta(&mut empty, 0, crate::CUSTOM_TYPE_EXT).unwrap();
+        let err = read_ns_payload(&mut empty.as_slice(), "table", 1).unwrap_err();
+        assert!(matches!(err, Error::TruncatedCustomType("namespace")));
+    }
+
+    #[rstest]
+    #[case(0.0)]
+    #[case(-0.0)]
+    #[case(f32::NAN)]
+    #[case(-f32::NAN)]
+    #[case(f32::INFINITY)]
+    #[case(f32::NEG_INFINITY)]
+    #[case(f32::MIN)]
+    #[case(f32::MAX)]
+    #[case(f32::MIN_POSITIVE)]
+    #[case(f32::from_bits(1))]
+    fn test_f32_bit_exact_round_trip(#[case] input: f32) {
+        use crate::ToBytes;
+
+        let mut buf = vec![];
+        input.to_bytes(&mut buf).unwrap();
+        let decoded = f32::from_bytes(&mut &buf[..]).unwrap();
+        assert_eq!(decoded.to_bits(), input.to_bits());
+    }
+
+    #[rstest]
+    #[case(0.0)]
+    #[case(-0.0)]
+    #[case(f64::NAN)]
+    #[case(-f64::NAN)]
+    #[case(f64::INFINITY)]
+    #[case(f64::NEG_INFINITY)]
+    #[case(f64::MIN)]
+    #[case(f64::MAX)]
+    #[case(f64::MIN_POSITIVE)]
+    #[case(f64::from_bits(1))]
+    fn test_f64_bit_exact_round_trip(#[case] input: f64) {
+        use crate::ToBytes;
+
+        let mut buf = vec![];
+        input.to_bytes(&mut buf).unwrap();
+        let decoded = f64::from_bytes(&mut &buf[..]).unwrap();
+        assert_eq!(decoded.to_bits(), input.to_bits());
+    }
+
+    #[rstest]
+    fn test_hex_round_trip() {
+        let value = vec!["a".to_string(), "bc".to_string()];
+        let hex = crate::encode::to_hex(&value).unwrap();
+        assert_eq!(hex, "92a161a26263");
+        assert_eq!(from_hex::<Vec<String>>(&hex).unwrap(), value);
+        assert_eq!(from_hex::<String>("A568656C6C6F").unwrap(), "hello");
+    }
+
+    #[rstest]
+    #[case("a")]
+    #[case("zz")]
+    #[case("a5é")]
+    #[case("+1")]
+    fn test_from_hex_invalid(#[case] input: &str) {
+        assert!(matches!(
+            from_hex::<String>(input),
+            Err(Error::InvalidHex(_))
+        ));
+    }
+
+    #[rstest]
+    #[case(31, vec![0xbf])]
+    #[case(32, vec![0xd9, 32])]
+    #[case(255, vec![0xd9, 255])]
+    #[case(256, vec![0xda, 1, 0])]
+    #[case(65535, vec![0xda, 255, 255])]
+    #[case(65536, vec![0xdb, 0, 1, 0, 0])]
+    fn test_string_length_boundaries(#[case] len: usize, #[case] header: Vec<u8>) {
+        use crate::ToBytes;
+
+        let input = "x".repeat(len);
+        let mut buf = vec![];
+        input.to_bytes(&mut buf).unwrap();
+        assert_eq!(&buf[..header.len()], &header[..]);
+        assert_eq!(buf.len(), header.len() + len);
+        assert_eq!(String::from_bytes(&mut &buf[..]).unwrap(), input);
+    }
+
+    #[rstest]
+    fn test_read_ns_payload_wrong_header() {
+        let encoded = |ext_type: i8, namespace: &str, id: u8| {
+            let mut data = vec![];
+            rmp::encode::write_str(&mut data, namespace).unwrap();
+            rmp::encode::write_uint(&mut data, id as u64).unwrap();
+            let mut buf = vec![];
+            rmpv::encode::write_value(&mut buf, &rmpv::Value::Ext(ext_type, data)).unwrap();
+            buf
+        };
+
+        let err = read_ns_payload(&mut &encoded(5, "n", 1)[..], "n", 1).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WrongExtType {
+                expected: 8,
+                found: 5
+            }
+        ));
+
+        let err = read_ns_payload(&mut &encoded(8, "m", 1)[..], "n", 1).unwrap_err();
+        match err {
+            Error::WrongNamespace { expected, found } => {
+                assert_eq!((expected.as_str(), found.as_str()), ("n", "m"));
+            }
+            other => panic!("Expected WrongNamespace, got {:?}", other),
+        }
+
+        let err = read_ns_payload(&mut &encoded(8, "n", 2)[..], "n", 1).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::WrongId {
+                expected: 1,
+                found: 2
+            }
+        ));
+    }
+
+    #[rstest]
+    fn test_from_reader_buffered() {
+        struct OneByteReader<'a> {
+            data: &'a [u8],
+        }
+
+        impl std::io::Read for OneByteReader<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                match (self.data.split_first(), buf.first_mut()) {
+                    (Some((byte, rest)), Some(slot)) => {
+                        *slot = *byte;
+                        self.data = rest;
+                        Ok(1)
+                    }
+                    _ => Ok(0),
+                }
+            }
+        }
+
+        let buf = vec![0x92, 0xa1, b'a', 0xa2, b'b', b'c'];
+        let mut reader = OneByteReader { data: &buf };
+        let decoded = Vec::<String>::from_reader_buffered(&mut reader).unwrap();
+        assert_eq!(decoded, vec!["a".to_string(), "bc".to_string()]);
+    }
+
+    #[rstest]
+    fn test_fixed_size_binary() {
+        use crate::ToBytes;
+
+        let mut buf = vec![];
+        (&[1u8, 2, 3, 4]).to_bytes(&mut buf).unwrap();
+        assert_eq!(<[u8; 4]>::from_bytes(&mut &buf[..]).unwrap(), [1, 2, 3, 4]);
+
+        let err = <[u8; 5]>::from_bytes(&mut &buf[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::LengthMismatch {
+                expected: 5,
+                found: 4
+            }
+        ));
+        assert_eq!(err.to_string(), "Expected 5 bytes, got 4");
+    }
+
+    #[rstest]
+    fn test_namespace_name_too_long() {
+        let mut data = vec![];
+        rmp::encode::write_str_len(&mut data, 100_000).unwrap();
+        let mut buf = vec![];
+        rmp::encode::write_ext_meta(&mut buf, data.len() as u32, crate::CUSTOM_TYPE_EXT).unwrap();
+        buf.extend_from_slice(&data);
+
+        let err = peek_ns_header(&mut buf.as_slice()).unwrap_err();
+        assert!(matches!(err, Error::NamespaceTooLong(100_000)));
+        let err = read_ns_payload(&mut buf.as_slice(), "table", 1).unwrap_err();
+        assert!(matches!(err, Error::NamespaceTooLong(100_000)));
+    }
+
+    #[rstest]
+    fn test_ns_payload_length_not_trusted() {
+        let mut header = vec![];
+        rmp::encode::write_str(&mut header, "table").unwrap();
+        rmp::encode::write_sint(&mut header, 1).unwrap();
+        let mut buf = vec![];
+        rmp::encode::write_ext_meta(&mut buf, u32::MAX, crate::CUSTOM_TYPE_EXT).unwrap();
+        buf.extend_from_slice(&header);
+        buf.extend_from_slice(&[1, 2, 3]);
+
+        let err = read_ns_payload(&mut buf.as_slice(), "table", 1).unwrap_err();
+        assert!(matches!(err, Error::UnexpectedEof));
+    }
+
+    #[rstest]
+    #[case(&[0xc7, 0x05, 0x08])]
+    #[case(&[0xc7, 0x05, 0x08, 0xa5, b't', b'a'])]
+    fn test_ns_header_truncated(#[case] data: &[u8]) {
+        let err = read_ns_payload(&mut &data[..], "table", 1).unwrap_err();
+        assert!(matches!(err, Error::UnexpectedEof));
+    }
+
+    #[rstest]
+    fn test_namespace_name_invalid_utf8() {
+        let data = [0xa2, 0xff, 0xfe, 0x01];
+        let mut buf = vec![];
+        rmp::encode::write_ext_meta(&mut buf, data.len() as u32, crate::CUSTOM_TYPE_EXT).unwrap();
+        buf.extend_from_slice(&data);
+
+        let err = read_ns_payload(&mut buf.as_slice(), "table", 1).unwrap_err();
+        assert!(matches!(err, Error::InvalidNamespaceName(_)));
+    }
+
+    #[rstest]
+    #[case(rmpv::Value::from(5), 5.0)]
+    #[case(rmpv::Value::from(-3), -3.0)]
+    #[case(rmpv::Value::from(u64::MAX), u64::MAX as f64)]
+    fn test_float_accepts_integers(#[case] value: rmpv::Value, #[case] expected: f64) {
+        assert_eq!(f64::from_value(value.clone()).unwrap(), expected);
+        assert_eq!(f32::from_value(value).unwrap(), expected as f32);
+    }
+
+    #[rstest]
+    #[case(std::net::Ipv6Addr::LOCALHOST)]
+    #[case(std::net::Ipv6Addr::UNSPECIFIED)]
+    #[case(std::net::Ipv6Addr::new(0x2001, 0xdb8, 0x85a3, 0x1, 0x2, 0x8a2e, 0x370, 0x7334))]
+    fn test_ipv6_round_trip(#[case] addr: std::net::Ipv6Addr) {
+        use crate::ToBytes;
+
+        let mut buf = vec![];
+        addr.to_bytes(&mut buf).unwrap();
+        assert_eq!(buf[..2], [0xc4, 16]);
+        assert_eq!(buf[2..], addr.octets());
+        assert_eq!(std::net::Ipv6Addr::from_bytes(&mut &buf[..]).unwrap(), addr);
+        assert_eq!(
+            std::net::IpAddr::from_bytes(&mut &buf[..]).unwrap(),
+            std::net::IpAddr::V6(addr)
+        );
+    }
+
+    #[rstest]
+    fn test_ipv4_round_trip() {
+        use crate::ToBytes;
+
+        let addr = std::net::IpAddr::from([192, 168, 0, 1]);
+        let mut buf = vec![];
+        addr.to_bytes(&mut buf).unwrap();
+        assert_eq!(buf, vec![0xc4, 4, 192, 168, 0, 1]);
+        assert_eq!(std::net::IpAddr::from_bytes(&mut &buf[..]).unwrap(), addr);
+    }
+
+    #[rstest]
+    #[case(vec![])]
+    #[case(vec![0; 5])]
+    #[case(vec![0; 15])]
+    fn test_ip_addr_rejects_other_lengths(#[case] data: Vec<u8>) {
+        assert!(std::net::IpAddr::from_value(rmpv::Value::Binary(data.clone())).is_err());
+        assert!(std::net::Ipv6Addr::from_value(rmpv::Value::Binary(data)).is_err());
+    }
+
+    #[rstest]
+    #[case(rmpv::Value::Nil, MsgpackType::Nil)]
+    #[case(rmpv::Value::from(false), MsgpackType::Bool)]
+    #[case(rmpv::Value::from(7), MsgpackType::Int)]
+    #[case(rmpv::Value::from(-70000), MsgpackType::Int)]
+    #[case(rmpv::Value::F32(1.5), MsgpackType::Float)]
+    #[case(rmpv::Value::from("hi"), MsgpackType::Str)]
+    #[case(rmpv::Value::Binary(vec![1]), MsgpackType::Bin)]
+    #[case(rmpv::Value::Array(vec![rmpv::Value::Nil]), MsgpackType::Array)]
+    #[case(rmpv::Value::Map(vec![]), MsgpackType::Map)]
+    #[case(rmpv::Value::Ext(8, vec![1, 2, 3]), MsgpackType::Ext(8))]
+    #[case(rmpv::Value::Ext(-1, vec![0; 4]), MsgpackType::Ext(-1))]
+    fn test_peek_type(#[case] value: rmpv::Value, #[case] expected: MsgpackType) {
+        let mut buf = vec![];
+        rmpv::encode::write_value(&mut buf, &value).unwrap();
+        assert_eq!(peek_type(&buf).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(vec![])]
+    #[case(vec![0xc1])]
+    #[case(vec![0xc7])]
+    fn test_peek_type_invalid(#[case] data: Vec<u8>) {
+        assert!(peek_type(&data).is_err());
+    }
+
+    #[rstest]
+    fn test_peek_type_reserved_marker() {
+        let err = peek_type(&[0xc1]).unwrap_err();
+        assert!(matches!(err, Error::ReservedMarker(0xc1)));
+        assert_eq!(err.to_string(), "Reserved msgpack marker 0xc1");
+    }
+
+    #[rstest]
+    fn test_vec_deque_round_trip() {
+        use crate::ToBytes;
+
+        let mut queue = std::collections::VecDeque::new();
+        queue.push_back(2u8);
+        queue.push_back(3);
+        queue.push_front(1);
+        let mut buf = vec![];
+        queue.to_bytes(&mut buf).unwrap();
+        assert_eq!(buf, vec![0x93, 1, 2, 3]);
+        assert_eq!(
+            std::collections::VecDeque::<u8>::from_bytes(&mut &buf[..]).unwrap(),
+            queue
+        );
+    }
+
+    #[rstest]
+    fn test_from_partial_chunks() {
+        use crate::ToBytes;
+
+        let value = vec!["alpha".to_string(), "beta".to_string()];
+        let mut encoded = vec![];
+        value.to_bytes(&mut encoded).unwrap();
+        encoded.push(0xc0);
+
+        let mut buffer = vec![];
+        let mut chunks = encoded.chunks(3);
+        let (decoded, consumed) = loop {
+            buffer.extend_from_slice(chunks.next().unwrap());
+            match Vec::<String>::from_partial(&buffer).unwrap() {
+                Partial::Complete(decoded, consumed) => break (decoded, consumed),
+                Partial::NeedMore => continue,
+            }
+        };
+        assert_eq!(decoded, value);
+        assert_eq!(consumed, encoded.len() - 1);
+        assert_eq!(Vec::<String>::from_partial(&[]).unwrap(), Partial::NeedMore);
+    }
+
+    #[rstest]
+    fn test_from_partial_invalid() {
+        assert!(u8::from_partial(&[0xa1, b'x']).is_err());
+    }
+
+    #[rstest]
+    fn test_wrapping_round_trip() {
+        use crate::ToBytes;
+
+        let value = std::num::Wrapping(u64::MAX) + std::num::Wrapping(2);
+        let mut buf = vec![];
+        value.to_bytes(&mut buf).unwrap();
+        assert_eq!(buf, vec![1]);
+        assert_eq!(
+            std::num::Wrapping::<u64>::from_bytes(&mut &buf[..]).unwrap(),
+            value
+        );
+
+        let mut buf = vec![];
+        std::num::Wrapping(u64::MAX).to_bytes(&mut buf).unwrap();
+        assert_eq!(
+            std::num::Wrapping::<u64>::from_bytes(&mut &buf[..]).unwrap(),
+            std::num::Wrapping(u64::MAX)
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[rstest]
+    #[case(uuid::Uuid::nil())]
+    #[case(uuid::Uuid::from_u128(0x67e5504410b1426f9247bb680e5fe0c8))]
+    fn test_uuid_round_trip(#[case] id: uuid::Uuid) {
+        use crate::ToBytes;
+
+        let mut buf = vec![];
+        id.to_bytes(&mut buf).unwrap();
+        assert_eq!(buf[..2], [0xc4, 16]);
+        assert_eq!(buf[2..], id.as_bytes()[..]);
+        assert_eq!(
+            <uuid::Uuid as FromBytes>::from_bytes(&mut &buf[..]).unwrap(),
+            id
+        );
+    }
+
+    #[cfg(feature = "uuid")]
+    #[rstest]
+    fn test_uuid_wrong_length() {
+        let value = rmpv::Value::Binary(vec![0; 15]);
+        assert!(uuid::Uuid::from_value(value).is_err());
+    }
+
+    #[rstest]
+    fn test_binary_heap_round_trip() {
+        use crate::ToBytes;
+
+        let heap = std::collections::BinaryHeap::from(vec![3u32, 1, 4, 1, 5]);
+        let mut buf = vec![];
+        heap.to_bytes(&mut buf).unwrap();
+        let decoded = std::collections::BinaryHeap::<u32>::from_bytes(&mut &buf[..]).unwrap();
+        assert_eq!(decoded.into_sorted_vec(), vec![1, 1, 3, 4, 5]);
+    }
+
+    #[rstest]
+    fn test_from_bytes_expected_family() {
+        let buf = [0xa3, b'a', b'b', b'c'];
+        let err = u32::from_bytes(&mut &buf[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::UnexpectedType {
+                expected: MsgpackFamily::Int,
+                found: MsgpackFamily::Str
+            }
+        ));
+        assert_eq!(String::from_bytes(&mut &buf[..]).unwrap(), "abc");
+
+        let err = bool::from_bytes(&mut &[0x01][..]).unwrap_err();
+        assert_eq!(err.to_string(), "Expected msgpack Bool, found Int");
+        assert_eq!(u8::from_bytes(&mut &[0xcc, 200][..]).unwrap(), 200);
+    }
+
+    #[rstest]
+    fn test_expected_family_checked_by_every_entry_point() {
+        let buf = [0xa3, b'a', b'b', b'c'];
+        let is_mismatch = |err: Error| {
+            matches!(
+                err,
+                Error::UnexpectedType {
+                    expected: MsgpackFamily::Int,
+                    found: MsgpackFamily::Str
+                }
+            )
+        };
+        assert!(is_mismatch(
+            u32::from_bytes_versioned(&mut &buf[..], 1).unwrap_err()
+        ));
+        assert!(is_mismatch(u32::from_partial(&buf[..2]).unwrap_err()));
+        assert!(is_mismatch(
+            u32::from_owned_bytes(buf.to_vec()).unwrap_err()
+        ));
+        assert!(matches!(u32::from_partial(&[]), Ok(Partial::NeedMore)));
+    }
+
+    #[rstest]
+    fn test_read_ns_payload_by_id() {
+        let mut data = vec![];
+        rmp::encode::write_uint(&mut data, 5).unwrap();
+        rmp::encode::write_uint(&mut data, 2).unwrap();
+        data.extend_from_slice(b"payload");
+        let mut buf = vec![];
+        rmpv::encode::write_value(&mut buf, &rmpv::Value::Ext(crate::CUSTOM_TYPE_EXT, data))
+            .unwrap();
+
+        let payload = read_ns_payload_by_id(&mut &buf[..], 5, 2).unwrap();
+        assert_eq!(payload, b"payload");
+
+        let err = read_ns_payload_by_id(&mut &buf[..], 6, 2).unwrap_err();
+        assert_eq!(err.to_string(), "Expected namespace '6', found '5'");
+        let err = read_ns_payload(&mut &buf[..], "5", 2).unwrap_err();
+        assert!(matches!(err, Error::WrongNamespace { .. }));
+    }
+
+    #[rstest]
+    #[case::empty(&[])]
+    #[case::truncated_str(&[0xa3, b'B'])]
+    fn test_from_bytes_unexpected_eof(#[case] data: &[u8]) {
+        assert!(matches!(
+            String::from_bytes(&mut &data[..]),
+            Err(Error::UnexpectedEof)
+        ));
+        assert!(matches!(
+            rmpv::Value::from_bytes(&mut &data[..]),
+            Err(Error::UnexpectedEof)
+        ));
+    }
+
+    #[rstest]
+    fn test_value_keeps_float_width() {
+        use crate::ToBytes;
+
+        let mut original = Vec::new();
+        3.14f32.to_bytes(&mut original).unwrap();
+        assert_eq!(original.len(), 5);
+
+        let value = rmpv::Value::from_bytes(&mut &original[..]).unwrap();
+        assert_eq!(value, rmpv::Value::F32(3.14));
+        let mut reencoded = Vec::new();
+        value.to_bytes(&mut reencoded).unwrap();
+        assert_eq!(reencoded, original);
+    }
+
+    #[rstest]
+    fn test_peek_ns_header() {
+        use crate::encode::{NamespaceEncodedValue, ToBytes};
+        use std::io::Read;
+
+        let ns_value = NamespaceEncodedValue {
+            namespace: "table",
+            id: 1,
+            value: vec![1, 2, 3],
+        };
+        let mut buf = Vec::new();
+        ns_value.to_bytes(&mut buf).unwrap();
+
+        let mut rd = &buf[..];
+        let header = peek_ns_header(&mut rd).unwrap();
+        assert_eq!(
+            header,
+            NsHeader {
+                namespace: NamespaceRef::Name("table".to_string()),
+                type_id: 1,
+                payload_len: 3,
+            }
+        );
+
+        let mut payload = vec![0; header.payload_len];
+        rd.read_exact(&mut payload).unwrap();
+        assert_eq!(payload, vec![1, 2, 3]);
+        assert!(rd.is_empty());
+    }
+
+    #[rstest]
+    fn test_peek_ns_header_id_form() {
+        // [Ext 8, uint namespace_id 2, uint type_id 5, 0xaa]
+        let buf: Vec<u8> = vec![0xc7, 3, 8, 0x02, 0x05, 0xaa];
+        let header = peek_ns_header(&mut &buf[..]).unwrap();
+        assert_eq!(header.namespace, NamespaceRef::Id(2));
+        assert_eq!(header.type_id, 5);
+        assert_eq!(header.payload_len, 1);
+    }
+
+    #[rstest]
+    #[case(0, 0xc7)]
+    #[case(1, 0xd6)]
+    #[case(252, 0xc7)]
+    #[case(253, 0xc8)]
+    #[case(65532, 0xc8)]
+    #[case(65533, 0xc9)]
+    #[case(70000, 0xc9)]
+    fn test_ns_payload_ext_size_boundaries(#[case] data_len: usize, #[case] marker: u8) {
+        use crate::encode::{NamespaceEncodedValue, ToBytes};
+
+        // 'n' namespace and id 1 add a 3 byte prefix to the ext payload
+        let value: Vec<u8> = (0..data_len).map(|i| i as u8).collect();
+        let ns_value = NamespaceEncodedValue {
+            namespace: "n",
+            id: 1,
+            value: value.clone(),
+        };
+        let mut buf = Vec::new();
+        ns_value.to_bytes(&mut buf).unwrap();
+        assert_eq!(buf[0], marker);
+
+        let payload = read_ns_payload(&mut &buf[..], "n", 1).unwrap();
+        assert_eq!(payload, value);
+    }
+
+    #[rstest]
+    #[case(vec![0xd6, 0xff, 0, 0, 0, 1], std::time::UNIX_EPOCH + std::time::Duration::from_secs(1))]
+    #[case(
+        vec![0xd7, 0xff, 0x77, 0x35, 0x94, 0x00, 0, 0, 0, 2],
+        std::time::UNIX_EPOCH + std::time::Duration::new(2, 500_000_000)
+    )]
+    #[case(
+        vec![0xc7, 12, 0xff, 0x1d, 0xcd, 0x65, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+        std::time::UNIX_EPOCH - std::time::Duration::from_millis(500)
+    )]
+    fn test_decoding_system_time(#[case] data: Vec<u8>, #[case] expected: std::time::SystemTime) {
+        let actual = std::time::SystemTime::from_bytes(&mut &data[..]).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[rstest]
+    fn test_decoding_system_time_bad_length() {
+        let data: Vec<u8> = vec![0xd5, 0xff, 0, 1];
+        let err = std::time::SystemTime::from_bytes(&mut &data[..]).unwrap_err();
+        assert!(matches!(err, Error::InvalidTimestamp(_)));
+        assert!(err.to_string().contains("got 2 bytes"));
+    }
+
+    #[rstest]
+    fn test_decoding_hashmap() {
+        let value1: Vec<u8> = vec![0b10000010, 0x01, 0x02, 0x03, 0x04]; // {1: 2, 3: 4}
+        let value2: Vec<u8> = vec![0b10000010, 0x03, 0x04, 0x01, 0x02]; // {3: 4, 1: 2}
+
+        let expected = {
+            let mut map = std::collections::HashMap::new();
+            map.insert(1, 2);
+            map.insert(3, 4);
+            map
+        };
+
+        let actual1 = std::collections::HashMap::<u8, u8>::from_bytes(&mut &value1[..]).unwrap();
+        let actual2 = std::collections::HashMap::<u8, u8>::from_bytes(&mut &value2[..]).unwrap();
+
+        assert!(
+            actual1 == expected,
+            "Expected: {:?}, Actual: {:?}",
+            expected,
+            actual1
+        );
+        assert!(
+            actual2 == expected,
+            "Expected: {:?}, Actual: {:?}",
             expected,
             actual2
         );
     }
 
+    #[rstest]
+    fn test_hashmap_integer_keys_round_trip() {
+        use crate::encode::ToBytes;
+
+        let mut map = std::collections::HashMap::new();
+        map.insert(1u32, "one".to_string());
+        map.insert(300u32, "three hundred".to_string());
+
+        let mut buf = Vec::new();
+        map.to_bytes(&mut buf).unwrap();
+
+        let value = rmpv::decode::read_value(&mut &buf[..]).unwrap();
+        for (key, _) in value.as_map().unwrap() {
+            assert!(key.is_u64(), "Expected an integer key, got {:?}", key);
+        }
+
+        let decoded = std::collections::HashMap::<u32, String>::from_bytes(&mut &buf[..]).unwrap();
+        assert_eq!(decoded, map);
+    }
+
+    #[rstest]
+    fn test_nil_keyed_map_round_trip() {
+        use crate::encode::ToBytes;
+
+        let map = rmpv::Value::Map(vec![
+            (rmpv::Value::Nil, rmpv::Value::from(1)),
+            (rmpv::Value::from("age"), rmpv::Value::from(2)),
+        ]);
+        let mut buf = Vec::new();
+        map.to_bytes(&mut buf).unwrap();
+        assert_eq!(rmpv::Value::from_bytes(&mut &buf[..]).unwrap(), map);
+
+        let pairs = crate::Pairs::<rmpv::Value, u32>::from_bytes(&mut &buf[..]).unwrap();
+        assert_eq!(pairs.0[0], (rmpv::Value::Nil, 1));
+    }
+
+    #[cfg(feature = "indexmap")]
+    #[rstest]
+    fn test_indexmap_round_trip_preserves_order() {
+        use crate::encode::ToBytes;
+
+        let mut map = indexmap::IndexMap::new();
+        map.insert("zebra".to_string(), 1u32);
+        map.insert("apple".to_string(), 2u32);
+        map.insert("mango".to_string(), 3u32);
+
+        let mut buf = Vec::new();
+        map.to_bytes(&mut buf).unwrap();
+
+        let decoded = indexmap::IndexMap::<String, u32>::from_bytes(&mut &buf[..]).unwrap();
+        let keys: Vec<&str> = decoded.keys().map(|k| k.as_str()).collect();
+        assert_eq!(keys, vec!["zebra", "apple", "mango"]);
+        assert_eq!(decoded, map);
+    }
+
     #[cfg(feature = "derive")]
     mod derive_tests {
         use super::*;
         use crate::encode::ToBytes;
-        use crate::ToBytesResult;
+        use crate::{ByteVec, ToBytesResult};
 
         #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
         struct Person {
@@ -243,9 +1602,55 @@ mod tests {
         #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
         struct Point(i32, i32);
 
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct OptionalPoint(Option<i32>, i32);
+
+        type Tags = std::collections::HashMap<String, Vec<u8>>;
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct PathTypes {
+            blobs: std::collections::HashMap<String, Vec<u8>>,
+            tags: Tags,
+            nested: Option<Vec<std::collections::VecDeque<u32>>>,
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct PathTuple(std::collections::HashMap<String, Vec<u8>>, Tags);
+
         #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
         struct Unit;
 
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        #[tobytes(repr_int)]
+        enum Color {
+            Red = 1,
+            Green = 2,
+        }
+
+        mod binary16 {
+            use crate::ToBytesResult;
+
+            pub fn to_bytes<W: std::io::Write>(value: &[u8; 16], wr: &mut W) -> ToBytesResult<()> {
+                rmp::encode::write_bin(wr, value)?;
+                Ok(())
+            }
+
+            pub fn from_value(value: rmpv::Value) -> ToBytesResult<[u8; 16]> {
+                let bytes = Vec::<u8>::try_from(value)?;
+                bytes.try_into().map_err(|_| {
+                    use std::io;
+                    io::Error::new(io::ErrorKind::InvalidData, "Expected 16 bytes").into()
+                })
+            }
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct Record {
+            #[tobytes(with = "binary16")]
+            id: [u8; 16],
+            name: String,
+        }
+
         #[rstest]
         fn test_derive_named_struct_round_trip() {
             let person = Person {
@@ -260,6 +1665,64 @@ mod tests {
             assert_eq!(person, decoded);
         }
 
+        #[rstest]
+        fn test_derive_mixed_with_dynamic_values() {
+            let person = Person {
+                name: "Alice".to_string(),
+                age: 30,
+            };
+            let mut buf = Vec::new();
+            rmp::encode::write_array_len(&mut buf, 2).unwrap();
+            person.to_bytes(&mut buf).unwrap();
+            rmpv::Value::from("extra").to_bytes(&mut buf).unwrap();
+
+            let values = Vec::<rmpv::Value>::from_bytes(&mut &buf[..]).unwrap();
+            assert_eq!(values[1], rmpv::Value::from("extra"));
+            let decoded = Person::from_value(values[0].clone()).unwrap();
+            assert_eq!(decoded, person);
+
+            let mut reencoded = Vec::new();
+            values.to_bytes(&mut reencoded).unwrap();
+            assert_eq!(reencoded, buf);
+        }
+
+        #[rstest]
+        fn test_derive_struct_rejects_non_string_key() {
+            let map = rmpv::Value::Map(vec![
+                (rmpv::Value::from("name"), rmpv::Value::from("Bob")),
+                (rmpv::Value::Nil, rmpv::Value::from(42)),
+            ]);
+            let err = Person::from_value(map).unwrap_err();
+            assert!(matches!(err, Error::NonStringMapKey(rmpv::Value::Nil)));
+            assert_eq!(err.to_string(), "Expected a string map key, found nil");
+        }
+
+        #[rstest]
+        fn test_derive_struct_rejects_string() {
+            let buf: Vec<u8> = vec![0xa3, b'B', b'o', b'b'];
+            let err = Person::from_bytes(&mut &buf[..]).unwrap_err();
+            assert!(matches!(err, Error::ExpectedMap { found: "string" }));
+            assert_eq!(err.to_string(), "Expected a map, found string");
+        }
+
+        #[rstest]
+        fn test_derive_struct_from_map_or_array() {
+            let expected = Person {
+                name: "Bob".to_string(),
+                age: 42,
+            };
+            let map_buf: Vec<u8> = vec![
+                0x82, 0xa4, b'n', b'a', b'm', b'e', 0xa3, b'B', b'o', b'b', 0xa3, b'a', b'g', b'e',
+                0x2a,
+            ];
+            let array_buf: Vec<u8> = vec![0x92, 0xa3, b'B', b'o', b'b', 0x2a];
+            assert_eq!(Person::from_bytes(&mut &map_buf[..]).unwrap(), expected);
+            assert_eq!(Person::from_bytes(&mut &array_buf[..]).unwrap(), expected);
+
+            let too_long: Vec<u8> = vec![0x93, 0xa3, b'B', b'o', b'b', 0x2a, 0xc0];
+            assert!(Person::from_bytes(&mut &too_long[..]).is_err());
+        }
+
         #[rstest]
         fn test_derive_tuple_struct_round_trip() {
             let point = Point(10, 20);
@@ -271,6 +1734,60 @@ mod tests {
             assert_eq!(point, decoded);
         }
 
+        #[rstest]
+        fn test_derive_path_and_alias_field_types() {
+            let mut blobs = std::collections::HashMap::new();
+            blobs.insert("a".to_string(), vec![1u8, 2]);
+            let value = PathTypes {
+                blobs: blobs.clone(),
+                tags: blobs.clone(),
+                nested: Some(vec![std::collections::VecDeque::from(vec![3, 4])]),
+            };
+            let mut buf = Vec::new();
+            value.to_bytes(&mut buf).unwrap();
+            assert_eq!(PathTypes::from_bytes(&mut &buf[..]).unwrap(), value);
+
+            let value = PathTuple(blobs.clone(), blobs);
+            let mut buf = Vec::new();
+            value.to_bytes(&mut buf).unwrap();
+            assert_eq!(PathTuple::from_bytes(&mut &buf[..]).unwrap(), value);
+        }
+
+        #[rstest]
+        fn test_derive_tuple_struct_option_field() {
+            let point = OptionalPoint(None, 5);
+            let mut buf = Vec::new();
+            point.to_bytes(&mut buf).unwrap();
+            assert_eq!(buf, vec![0x92, 0xc0, 0x05]);
+            assert_eq!(OptionalPoint::from_bytes(&mut &buf[..]).unwrap(), point);
+
+            let point = OptionalPoint(Some(-1), 5);
+            let mut buf = Vec::new();
+            point.to_bytes(&mut buf).unwrap();
+            assert_eq!(OptionalPoint::from_bytes(&mut &buf[..]).unwrap(), point);
+        }
+
+        #[rstest]
+        fn test_derive_missing_option_field_is_none() {
+            let value = rmpv::Value::Map(vec![
+                ("blobs".into(), rmpv::Value::Map(vec![])),
+                ("tags".into(), rmpv::Value::Map(vec![])),
+            ]);
+            let decoded = PathTypes::from_value(value).unwrap();
+            assert_eq!(decoded.nested, None);
+
+            let value = rmpv::Value::Map(vec![("blobs".into(), rmpv::Value::Map(vec![]))]);
+            let err = PathTypes::from_value(value).unwrap_err();
+            assert!(err.to_string().contains("Missing field: tags"));
+        }
+
+        #[rstest]
+        fn test_derive_tuple_struct_rejects_extra_fields() {
+            let buf: Vec<u8> = vec![0x93, 0x01, 0x02, 0x03];
+            let err = Point::from_bytes(&mut &buf[..]).unwrap_err();
+            assert!(err.to_string().contains("Expected 2 fields, got 3"));
+        }
+
         #[rstest]
         fn test_derive_unit_struct_round_trip() {
             let unit = Unit;
@@ -281,5 +1798,358 @@ mod tests {
             let decoded = Unit::from_bytes(&mut &buf[..]).unwrap();
             assert_eq!(unit, decoded);
         }
+
+        #[rstest]
+        fn test_derive_repr_int_enum_round_trip() {
+            for color in [Color::Red, Color::Green] {
+                let mut buf = Vec::new();
+                color.to_bytes(&mut buf).unwrap();
+
+                let decoded = Color::from_bytes(&mut &buf[..]).unwrap();
+                assert_eq!(color, decoded);
+            }
+        }
+
+        #[rstest]
+        fn test_derive_repr_int_enum_unknown_discriminant() {
+            let buf: Vec<u8> = vec![0x03];
+            let err = Color::from_bytes(&mut &buf[..]).unwrap_err();
+            assert!(err.to_string().contains("Unknown discriminant 3"));
+        }
+
+        #[rstest]
+        fn test_derive_with_module_round_trip() {
+            let record = Record {
+                id: [7u8; 16],
+                name: "Alice".to_string(),
+            };
+
+            let mut buf = Vec::new();
+            record.to_bytes(&mut buf).unwrap();
+
+            let value = rmpv::decode::read_value(&mut &buf[..]).unwrap();
+            let id = value
+                .as_map()
+                .unwrap()
+                .iter()
+                .find(|(k, _)| k.as_str() == Some("id"))
+                .map(|(_, v)| v.clone())
+                .unwrap();
+            assert_eq!(id, rmpv::Value::Binary(vec![7u8; 16]));
+
+            let decoded = Record::from_bytes(&mut &buf[..]).unwrap();
+            assert_eq!(record, decoded);
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct Note {
+            title: String,
+            body: String,
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct Blob {
+            name: String,
+            data: ByteVec,
+        }
+
+        #[rstest]
+        fn test_derive_from_owned_bytes_large_field() {
+            let blob = Blob {
+                name: "big".to_string(),
+                data: ByteVec(Some((0..100_000u32).map(|i| i as u8).collect())),
+            };
+
+            let mut buf = Vec::new();
+            blob.to_bytes(&mut buf).unwrap();
+            let ptr = buf.as_ptr();
+
+            let decoded = Blob::from_owned_bytes(buf).unwrap();
+            assert_eq!(blob, decoded);
+            assert_eq!(decoded.data.0.as_ref().unwrap().as_ptr(), ptr);
+        }
+
+        #[rstest]
+        fn test_derive_from_owned_bytes_large_string_field() {
+            let note = Note {
+                title: "t".to_string(),
+                body: "x".repeat(10_000),
+            };
+
+            let mut buf = Vec::new();
+            note.to_bytes(&mut buf).unwrap();
+            let ptr = buf.as_ptr();
+
+            let decoded = Note::from_owned_bytes(buf).unwrap();
+            assert_eq!(note, decoded);
+            assert_eq!(decoded.body.as_ptr(), ptr);
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct Msg<'a> {
+            text: std::borrow::Cow<'a, str>,
+        }
+
+        #[rstest]
+        fn test_derive_cow_field_round_trip() {
+            let text = "borrowed".to_string();
+            for msg in [
+                Msg {
+                    text: std::borrow::Cow::Borrowed(&text),
+                },
+                Msg {
+                    text: std::borrow::Cow::Owned("owned".to_string()),
+                },
+            ] {
+                let mut buf = Vec::new();
+                msg.to_bytes(&mut buf).unwrap();
+
+                let decoded = Msg::from_bytes(&mut &buf[..]).unwrap();
+                assert_eq!(msg, decoded);
+            }
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct Wrap<T> {
+            value: T,
+            items: Vec<T>,
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct Pair<A, B>(A, B);
+
+        #[rstest]
+        fn test_derive_type_generic_round_trip() {
+            let wrap = Wrap {
+                value: 5u32,
+                items: vec![1, 2],
+            };
+            let mut buf = Vec::new();
+            wrap.to_bytes(&mut buf).unwrap();
+            assert_eq!(Wrap::<u32>::from_bytes(&mut &buf[..]).unwrap(), wrap);
+
+            let pair = Pair(
+                "a".to_string(),
+                Wrap {
+                    value: true,
+                    items: vec![],
+                },
+            );
+            let mut buf = Vec::new();
+            pair.to_bytes(&mut buf).unwrap();
+            assert_eq!(
+                Pair::<String, Wrap<bool>>::from_bytes(&mut &buf[..]).unwrap(),
+                pair
+            );
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct StaticMsg {
+            text: std::borrow::Cow<'static, str>,
+        }
+
+        #[rstest]
+        fn test_derive_static_cow_decodes_owned() {
+            let msg = StaticMsg {
+                text: std::borrow::Cow::Borrowed("static"),
+            };
+
+            let mut buf = Vec::new();
+            msg.to_bytes(&mut buf).unwrap();
+
+            let decoded = StaticMsg::from_bytes(&mut &buf[..]).unwrap();
+            assert_eq!(msg, decoded);
+            assert!(matches!(decoded.text, std::borrow::Cow::Owned(_)));
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct Profile {
+            name: String,
+            #[tobytes(since = 2)]
+            email: String,
+        }
+
+        #[rstest]
+        fn test_derive_since_encode_v1_decode_v2() {
+            let profile = Profile {
+                name: "Alice".to_string(),
+                email: "alice@example.com".to_string(),
+            };
+
+            let mut buf = Vec::new();
+            profile.to_bytes_versioned(&mut buf, 1).unwrap();
+            assert_eq!(buf[0], 0b10000001); // fixmap with 1 entry
+
+            let decoded = Profile::from_bytes_versioned(&mut &buf[..], 2).unwrap();
+            assert_eq!(decoded.name, "Alice");
+            assert_eq!(decoded.email, "");
+        }
+
+        #[rstest]
+        fn test_derive_since_encode_v2_decode_v1() {
+            let profile = Profile {
+                name: "Alice".to_string(),
+                email: "alice@example.com".to_string(),
+            };
+
+            let mut buf = Vec::new();
+            profile.to_bytes_versioned(&mut buf, 2).unwrap();
+            assert_eq!(buf[0], 0b10000010); // fixmap with 2 entries
+
+            let decoded = Profile::from_bytes_versioned(&mut &buf[..], 1).unwrap();
+            assert_eq!(decoded.name, "Alice");
+            assert_eq!(decoded.email, "");
+
+            let decoded = Profile::from_bytes_versioned(&mut &buf[..], 2).unwrap();
+            assert_eq!(profile, decoded);
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct Team {
+            members: Vec<Profile>,
+            lead: Option<Profile>,
+        }
+
+        #[rstest]
+        fn test_derive_since_reaches_container_elements() {
+            let profile = || Profile {
+                name: "Alice".to_string(),
+                email: "alice@example.com".to_string(),
+            };
+            let team = Team {
+                members: vec![profile(), profile()],
+                lead: Some(profile()),
+            };
+
+            let mut buf = Vec::new();
+            team.to_bytes_versioned(&mut buf, 1).unwrap();
+            let value = rmpv::Value::from_bytes(&mut &buf[..]).unwrap();
+            let field = |name: &str| {
+                value
+                    .as_map()
+                    .unwrap()
+                    .iter()
+                    .find(|(key, _)| key.as_str() == Some(name))
+                    .map(|(_, val)| val.clone())
+                    .unwrap()
+            };
+            for member in field("members").as_array().unwrap() {
+                assert_eq!(member.as_map().unwrap().len(), 1);
+            }
+            assert_eq!(field("lead").as_map().unwrap().len(), 1);
+
+            let decoded = Team::from_bytes_versioned(&mut &buf[..], 1).unwrap();
+            assert!(decoded.members.iter().all(|member| member.email.is_empty()));
+            assert_eq!(decoded.lead.unwrap().email, "");
+
+            let mut buf = Vec::new();
+            team.to_bytes_versioned(&mut buf, 2).unwrap();
+            let decoded = Team::from_bytes_versioned(&mut &buf[..], 1).unwrap();
+            assert!(decoded.members.iter().all(|member| member.email.is_empty()));
+            assert_eq!(Team::from_bytes(&mut &buf[..]).unwrap(), team);
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct Owner(Profile);
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        #[tobytes(tag = "array")]
+        enum Contact {
+            Person(Profile),
+            Pair(Profile, Profile),
+            Named { profile: Profile },
+        }
+
+        #[rstest]
+        fn test_derive_since_reaches_tuple_structs_and_enums() {
+            let profile = || Profile {
+                name: "Alice".to_string(),
+                email: "alice@example.com".to_string(),
+            };
+
+            let mut buf = Vec::new();
+            Owner(profile()).to_bytes_versioned(&mut buf, 1).unwrap();
+            // fixarray of 1, then a fixmap with only the v1 field
+            assert_eq!(&buf[..2], &[0b10010001, 0b10000001]);
+            let decoded = Owner::from_bytes_versioned(&mut &buf[..], 1).unwrap();
+            assert_eq!(decoded.0.email, "");
+
+            let mut buf = Vec::new();
+            Owner(profile()).to_bytes(&mut buf).unwrap();
+            let decoded = Owner::from_bytes_versioned(&mut &buf[..], 1).unwrap();
+            assert_eq!(decoded.0.email, "");
+
+            for contact in [
+                Contact::Person(profile()),
+                Contact::Pair(profile(), profile()),
+                Contact::Named { profile: profile() },
+            ] {
+                let mut buf = Vec::new();
+                contact.to_bytes_versioned(&mut buf, 1).unwrap();
+                let decoded = Contact::from_bytes_versioned(&mut &buf[..], 2).unwrap();
+                let profiles = match decoded {
+                    Contact::Person(a) | Contact::Named { profile: a } => vec![a],
+                    Contact::Pair(a, b) => vec![a, b],
+                };
+                assert!(profiles
+                    .iter()
+                    .all(|p| p.name == "Alice" && p.email.is_empty()));
+
+                let mut buf = Vec::new();
+                contact.to_bytes(&mut buf).unwrap();
+                let decoded = Contact::from_bytes_versioned(&mut &buf[..], 1).unwrap();
+                let profiles = match decoded {
+                    Contact::Person(a) | Contact::Named { profile: a } => vec![a],
+                    Contact::Pair(a, b) => vec![a, b],
+                };
+                assert!(profiles.iter().all(|p| p.email.is_empty()));
+            }
+        }
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        #[tobytes(tag = "array")]
+        enum Shape {
+            Empty,
+            Circle(f64),
+            Line(i32, i32),
+            Rect { width: u32, height: u32 },
+        }
+
+        #[rstest]
+        fn test_derive_array_tag_enum_round_trip() {
+            for shape in [
+                Shape::Empty,
+                Shape::Circle(1.5),
+                Shape::Line(-1, 4),
+                Shape::Rect {
+                    width: 3,
+                    height: 4,
+                },
+            ] {
+                let mut buf = Vec::new();
+                shape.to_bytes(&mut buf).unwrap();
+                assert_eq!(buf[0], 0b10010010); // fixarray with 2 elements
+
+                let decoded = Shape::from_bytes(&mut &buf[..]).unwrap();
+                assert_eq!(shape, decoded);
+            }
+        }
+
+        #[rstest]
+        fn test_derive_array_tag_enum_wire_format() {
+            let mut buf = Vec::new();
+            Shape::Line(1, 2).to_bytes(&mut buf).unwrap();
+            assert_eq!(
+                buf,
+                vec![0x92, 0xa4, b'L', b'i', b'n', b'e', 0x92, 0x01, 0x02]
+            );
+        }
+
+        #[rstest]
+        fn test_derive_array_tag_enum_unknown_variant() {
+            let buf: Vec<u8> = vec![0x92, 0xa3, b'H', b'e', b'x', 0xc0];
+            let err = Shape::from_bytes(&mut &buf[..]).unwrap_err();
+            assert!(err.to_string().contains("Unknown variant Hex for Shape"));
+        }
     }
 }
@@ -1,9 +1,144 @@
+use crate::error::Error;
 use crate::ToBytesResult;
 use rmpv::encode::write_value_ref;
 use std::io::Write;
 
 pub trait ToBytes {
     fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()>;
+
+    /// Encodes the value as of schema `version`. Derived structs omit fields
+    /// marked `#[tobytes(since = N)]` when `version < N`, containers pass the
+    /// version on to their elements, and other types ignore it.
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        let _ = version;
+        self.to_bytes(wr)
+    }
+
+    /// Encodes into a new buffer, failing with `Error::OutputTooLarge` as
+    /// soon as the output would exceed `max_bytes`.
+    fn to_bytes_capped(&self, max_bytes: usize) -> ToBytesResult<Vec<u8>> {
+        let mut wr = CappedWriter {
+            buf: Vec::new(),
+            max_bytes,
+            exceeded: false,
+        };
+        match self.to_bytes(&mut wr) {
+            Ok(()) => Ok(wr.buf),
+            Err(_) if wr.exceeded => Err(Error::OutputTooLarge(max_bytes)),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Encodes `value` and returns the bytes as a lowercase hex string.
+pub fn to_hex<T: ToBytes + ?Sized>(value: &T) -> ToBytesResult<String> {
+    let mut buf = vec![];
+    value.to_bytes(&mut buf)?;
+    Ok(buf.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Writes a map of `len` entries from `iter` without collecting it first.
+/// Fails if `iter` yields a different number of entries, in which case the
+/// output written so far is not a valid map.
+pub fn encode_map_from_iter<W, I, K, V>(wr: &mut W, len: u32, iter: I) -> ToBytesResult<()>
+where
+    W: Write,
+    I: IntoIterator<Item = (K, V)>,
+    K: ToBytes,
+    V: ToBytes,
+{
+    rmp::encode::write_map_len(wr, len)?;
+    let mut written = 0u32;
+    for (key, value) in iter {
+        if written == len {
+            return Err(map_len_mismatch(len, written.saturating_add(1)));
+        }
+        key.to_bytes(wr)?;
+        value.to_bytes(wr)?;
+        written += 1;
+    }
+    if written != len {
+        return Err(map_len_mismatch(len, written));
+    }
+    Ok(())
+}
+
+fn map_len_mismatch(expected: u32, found: u32) -> Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!("Expected {} map entries, got {}", expected, found),
+    )
+    .into()
+}
+
+/// Implements `ToBytes` and `FromBytes` for a single-field tuple struct by
+/// delegating to the inner type, so `Meters(1.5)` encodes exactly like
+/// `1.5`:
+///
+/// ```
+/// struct Meters(f64);
+/// tobytes::impl_transparent_tobytes!(Meters => f64);
+/// ```
+#[macro_export]
+macro_rules! impl_transparent_tobytes {
+    ($outer:ident => $inner:ty) => {
+        impl $crate::ToBytes for $outer {
+            fn to_bytes<W: ::std::io::Write>(&self, wr: &mut W) -> $crate::ToBytesResult<()> {
+                $crate::ToBytes::to_bytes(&self.0, wr)
+            }
+
+            fn to_bytes_versioned<W: ::std::io::Write>(
+                &self,
+                wr: &mut W,
+                version: u32,
+            ) -> $crate::ToBytesResult<()> {
+                $crate::ToBytes::to_bytes_versioned(&self.0, wr, version)
+            }
+        }
+
+        impl $crate::FromBytes for $outer {
+            type Output = $outer;
+
+            fn from_value(
+                value: $crate::__private::rmpv::Value,
+            ) -> $crate::ToBytesResult<Self::Output> {
+                Ok($outer(<$inner as $crate::FromBytes>::from_value(value)?))
+            }
+
+            fn from_value_versioned(
+                value: $crate::__private::rmpv::Value,
+                version: u32,
+            ) -> $crate::ToBytesResult<Self::Output> {
+                Ok($outer(<$inner as $crate::FromBytes>::from_value_versioned(
+                    value, version,
+                )?))
+            }
+        }
+    };
+}
+
+struct CappedWriter {
+    buf: Vec<u8>,
+    max_bytes: usize,
+    exceeded: bool,
+}
+
+impl Write for CappedWriter {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+        if self.buf.len() + data.len() > self.max_bytes {
+            self.exceeded = true;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::WriteZero,
+                "output size cap exceeded",
+            ));
+        }
+        self.buf.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
 }
 
 macro_rules! impl_primitive_encode {
@@ -60,29 +195,234 @@ impl ToBytes for String {
     }
 }
 
+impl ToBytes for std::borrow::Cow<'_, str> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        let value: rmpv::ValueRef = self.as_ref().into();
+        write_value_ref(wr, &value)?;
+        Ok(())
+    }
+}
+
+impl<T: ToBytes> ToBytes for std::num::Wrapping<T> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        self.0.to_bytes_versioned(wr, version)
+    }
+}
+
+impl<T: ToBytes> ToBytes for Option<T> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        match self {
+            Some(value) => value.to_bytes_versioned(wr, version),
+            None => {
+                rmp::encode::write_nil(wr)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Encodes the referenced value, or nil if it has already been dropped.
+impl<T: ToBytes> ToBytes for std::rc::Weak<T> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        match self.upgrade() {
+            Some(value) => value.to_bytes_versioned(wr, version),
+            None => {
+                rmp::encode::write_nil(wr)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Encodes the referenced value, or nil if it has already been dropped.
+impl<T: ToBytes> ToBytes for std::sync::Weak<T> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        match self.upgrade() {
+            Some(value) => value.to_bytes_versioned(wr, version),
+            None => {
+                rmp::encode::write_nil(wr)?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Encodes using the msgpack timestamp extension, picking the smallest of
+/// the 32, 64 and 96-bit forms that can represent the time.
+impl ToBytes for std::time::SystemTime {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        let (secs, nanos) = match self.duration_since(std::time::UNIX_EPOCH) {
+            Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+            Err(e) => {
+                let d = e.duration();
+                if d.subsec_nanos() == 0 {
+                    (-(d.as_secs() as i64), 0)
+                } else {
+                    (-(d.as_secs() as i64) - 1, 1_000_000_000 - d.subsec_nanos())
+                }
+            }
+        };
+        if secs >> 34 == 0 {
+            let data64 = ((nanos as u64) << 34) | secs as u64;
+            if data64 & 0xffffffff00000000 == 0 {
+                rmp::encode::write_ext_meta(wr, 4, crate::TIMESTAMP_EXT)?;
+                wr.write_all(&(data64 as u32).to_be_bytes())?;
+            } else {
+                rmp::encode::write_ext_meta(wr, 8, crate::TIMESTAMP_EXT)?;
+                wr.write_all(&data64.to_be_bytes())?;
+            }
+        } else {
+            rmp::encode::write_ext_meta(wr, 12, crate::TIMESTAMP_EXT)?;
+            wr.write_all(&nanos.to_be_bytes())?;
+            wr.write_all(&secs.to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+/// IP addresses are written as 4 or 16 byte msgpack binary in network
+/// order. An IPv6 zone/scope id is not part of `Ipv6Addr` and so is never
+/// serialized.
+impl ToBytes for std::net::IpAddr {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        match self {
+            std::net::IpAddr::V4(addr) => addr.to_bytes(wr),
+            std::net::IpAddr::V6(addr) => addr.to_bytes(wr),
+        }
+    }
+}
+
+impl ToBytes for std::net::Ipv4Addr {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        rmp::encode::write_bin(wr, &self.octets())?;
+        Ok(())
+    }
+}
+
+impl ToBytes for std::net::Ipv6Addr {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        rmp::encode::write_bin(wr, &self.octets())?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl ToBytes for uuid::Uuid {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        rmp::encode::write_bin(wr, self.as_bytes())?;
+        Ok(())
+    }
+}
+
 impl<T: ToBytes> ToBytes for Vec<T> {
     fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        let len = self.len() as u32;
+        rmp::encode::write_array_len(wr, len)?;
+        for item in self {
+            item.to_bytes_versioned(wr, version)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: ToBytes> ToBytes for std::collections::VecDeque<T> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
         let len = self.len() as u32;
         rmp::encode::write_array_len(wr, len)?;
         for item in self {
-            item.to_bytes(wr)?;
+            item.to_bytes_versioned(wr, version)?;
         }
         Ok(())
     }
 }
 
+/// Elements are written in the heap's internal order, so a round trip keeps
+/// the same elements but not necessarily the same array order.
+impl<T: ToBytes> ToBytes for std::collections::BinaryHeap<T> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        let len = self.len() as u32;
+        rmp::encode::write_array_len(wr, len)?;
+        for item in self {
+            item.to_bytes_versioned(wr, version)?;
+        }
+        Ok(())
+    }
+}
+
+/// Keys are written with their own `ToBytes` impl, so `HashMap<u32, V>`
+/// produces msgpack integer keys rather than strings. Readers must accept
+/// non-string map keys to decode these; the Python `Codec.loads` uses
+/// msgpack's default `strict_map_key=True`, which only allows `str`/`bytes`
+/// keys, so integer-keyed maps are only portable to peers that relax it.
 impl<K: ToBytes, V: ToBytes> ToBytes for std::collections::HashMap<K, V> {
     fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
         let len = self.len() as u32;
         rmp::encode::write_map_len(wr, len)?;
         for (key, value) in self {
-            key.to_bytes(wr)?;
-            value.to_bytes(wr)?;
+            key.to_bytes_versioned(wr, version)?;
+            value.to_bytes_versioned(wr, version)?;
         }
         Ok(())
     }
 }
 
+#[cfg(feature = "indexmap")]
+impl<K: ToBytes, V: ToBytes> ToBytes for indexmap::IndexMap<K, V> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        let len = self.len() as u32;
+        rmp::encode::write_map_len(wr, len)?;
+        for (key, value) in self {
+            key.to_bytes_versioned(wr, version)?;
+            value.to_bytes_versioned(wr, version)?;
+        }
+        Ok(())
+    }
+}
+
+/// Dynamic msgpack values, so they can be mixed with native types.
+impl ToBytes for rmpv::Value {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        rmpv::encode::write_value(wr, self)?;
+        Ok(())
+    }
+}
+
 impl<const S: usize> ToBytes for &[u8; S] {
     fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
         let value: rmpv::ValueRef = rmpv::ValueRef::Binary(self.as_ref());
@@ -105,13 +445,19 @@ impl ToBytes for NamespaceEncodedValue {
         rmp::encode::write_str(&mut pfx_buf, self.namespace)?;
         rmp::encode::write_sint(&mut pfx_buf, self.id as i64)?;
         let total_len = pfx_buf.len() + self.value.len();
-        rmp::encode::write_ext_meta(wr, total_len as u32, CUSTOM_TYPE_EXT)?;
+        rmp::encode::write_ext_meta(wr, ext32_len(total_len)?, CUSTOM_TYPE_EXT)?;
         wr.write_all(&pfx_buf)?;
         wr.write_all(&self.value)?;
         Ok(())
     }
 }
 
+/// Checks that a custom type body fits in an ext32 length.
+fn ext32_len(len: usize) -> ToBytesResult<u32> {
+    u32::try_from(len).map_err(|_| Error::ExtTooLarge(len))
+}
+
+#[allow(dead_code)]
 pub struct NamespaceValue<T: ToBytes> {
     namespace: &'static str,
     id: u32,
@@ -124,13 +470,14 @@ impl<T: ToBytes> ToBytes for NamespaceValue<T> {
         rmp::encode::write_str(&mut buf, self.namespace)?;
         rmp::encode::write_sint(&mut buf, self.id as i64)?;
         self.value.to_bytes(&mut buf)?;
-        rmp::encode::write_ext_meta(wr, buf.len() as u32, CUSTOM_TYPE_EXT)?;
+        rmp::encode::write_ext_meta(wr, ext32_len(buf.len())?, CUSTOM_TYPE_EXT)?;
         wr.write_all(&buf)?;
         Ok(())
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::approx_constant)]
 mod tests {
     use super::*;
     use compose_idents::compose;
@@ -215,10 +562,209 @@ mod tests {
         );
     }
 
+    #[rstest]
+    #[case(std::time::Duration::from_secs(1), vec![0xd6, 0xff, 0, 0, 0, 1])]
+    #[case(
+        std::time::Duration::new(2, 500_000_000),
+        vec![0xd7, 0xff, 0x77, 0x35, 0x94, 0x00, 0, 0, 0, 2]
+    )]
+    #[case(
+        std::time::Duration::from_secs(1 << 34),
+        vec![0xc7, 12, 0xff, 0, 0, 0, 0, 0, 0, 0, 0x04, 0, 0, 0, 0]
+    )]
+    fn test_encoding_system_time(
+        #[case] since_epoch: std::time::Duration,
+        #[case] expected: Vec<u8>,
+    ) {
+        let time = std::time::UNIX_EPOCH + since_epoch;
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        time.to_bytes(buf).unwrap();
+        assert_eq!(buf, &expected);
+    }
+
+    #[rstest]
+    fn test_encoding_system_time_pre_epoch() {
+        let time = std::time::UNIX_EPOCH - std::time::Duration::from_millis(500);
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        time.to_bytes(buf).unwrap();
+        let mut expected = vec![0xc7, 12, 0xff];
+        expected.extend_from_slice(&500_000_000u32.to_be_bytes());
+        expected.extend_from_slice(&(-1i64).to_be_bytes());
+        assert_eq!(buf, &expected);
+    }
+
+    #[rstest]
+    #[case(-0.0, vec![0xcb, 0x80, 0, 0, 0, 0, 0, 0, 0])]
+    #[case(f64::INFINITY, vec![0xcb, 0x7f, 0xf0, 0, 0, 0, 0, 0, 0])]
+    #[case(f64::NEG_INFINITY, vec![0xcb, 0xff, 0xf0, 0, 0, 0, 0, 0, 0])]
+    #[case(f64::from_bits(1), vec![0xcb, 0, 0, 0, 0, 0, 0, 0, 1])]
+    fn test_encoding_f64_special(#[case] input: f64, #[case] expected: Vec<u8>) {
+        let mut buf = vec![];
+        input.to_bytes(&mut buf).unwrap();
+        assert_eq!(buf, expected);
+    }
+
+    #[rstest]
+    fn test_to_hex() {
+        assert_eq!(to_hex(&"hello").unwrap(), "a568656c6c6f");
+        assert_eq!(to_hex(&vec![1u8, 255u8]).unwrap(), "9201ccff");
+    }
+
+    #[rstest]
+    fn test_encoding_dynamic_values() {
+        let values = vec![
+            rmpv::Value::Nil,
+            rmpv::Value::from("a"),
+            rmpv::Value::Ext(CUSTOM_TYPE_EXT, vec![1, 2]),
+            rmpv::Value::Map(vec![(rmpv::Value::from(1), rmpv::Value::from(true))]),
+        ];
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        values.to_bytes(buf).unwrap();
+
+        let mut expected = vec![];
+        rmpv::encode::write_value(&mut expected, &rmpv::Value::Array(values)).unwrap();
+        assert_eq!(buf, &expected);
+    }
+
+    #[rstest]
+    fn test_to_bytes_capped() {
+        let value = vec!["x".repeat(100); 3];
+        let buf = value.to_bytes_capped(1024).unwrap();
+        let mut expected = vec![];
+        value.to_bytes(&mut expected).unwrap();
+        assert_eq!(buf, expected);
+
+        let err = value.to_bytes_capped(150).unwrap_err();
+        assert!(matches!(err, Error::OutputTooLarge(150)));
+        assert!(value.to_bytes_capped(expected.len()).is_ok());
+    }
+
+    #[rstest]
+    fn test_impl_transparent_tobytes() {
+        use crate::FromBytes;
+
+        #[derive(Debug, PartialEq)]
+        struct Meters(f64);
+        crate::impl_transparent_tobytes!(Meters => f64);
+
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        Meters(1.5).to_bytes(buf).unwrap();
+        let expected: &mut Vec<u8> = &mut Vec::new();
+        1.5f64.to_bytes(expected).unwrap();
+        assert_eq!(buf, expected);
+        assert_eq!(Meters::from_bytes(&mut &buf[..]).unwrap(), Meters(1.5));
+    }
+
+    #[rstest]
+    fn test_encode_map_from_iter() {
+        let mut map = std::collections::HashMap::new();
+        map.insert("only".to_string(), 7u32);
+        let mut expected = Vec::new();
+        map.to_bytes(&mut expected).unwrap();
+
+        let mut buf = Vec::new();
+        encode_map_from_iter(&mut buf, 1, map.iter().map(|(k, v)| (k.clone(), *v))).unwrap();
+        assert_eq!(buf, expected);
+
+        let entries = (0..3u32).map(|i| (i, i * 10));
+        let mut buf = Vec::new();
+        encode_map_from_iter(&mut buf, 3, entries).unwrap();
+        let decoded =
+            <std::collections::HashMap<u32, u32> as crate::FromBytes>::from_bytes(&mut &buf[..])
+                .unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[&2], 20);
+
+        assert!(encode_map_from_iter(&mut Vec::new(), 2, [(1u8, 1u8)]).is_err());
+        assert!(encode_map_from_iter(&mut Vec::new(), 1, [(1u8, 1u8), (2, 2)]).is_err());
+    }
+
+    #[rstest]
+    fn test_encoding_weak() {
+        let strong = std::rc::Rc::new(42u8);
+        let weak = std::rc::Rc::downgrade(&strong);
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        weak.to_bytes(buf).unwrap();
+        assert_eq!(buf.as_slice(), &[42]);
+
+        drop(strong);
+        buf.clear();
+        weak.to_bytes(buf).unwrap();
+        assert_eq!(buf.as_slice(), &[0xc0]);
+    }
+
+    #[rstest]
+    fn test_encoding_sync_weak() {
+        let strong = std::sync::Arc::new("hi".to_string());
+        let weak = std::sync::Arc::downgrade(&strong);
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        weak.to_bytes(buf).unwrap();
+        assert_eq!(buf.as_slice(), &[0xa2, 0x68, 0x69]);
+
+        drop(strong);
+        buf.clear();
+        weak.to_bytes(buf).unwrap();
+        assert_eq!(buf.as_slice(), &[0xc0]);
+    }
+
+    #[rstest]
+    fn test_ext32_len_limit() {
+        assert_eq!(ext32_len(u32::MAX as usize).unwrap(), u32::MAX);
+        let too_long = u32::MAX as usize + 1;
+        assert!(matches!(ext32_len(too_long), Err(Error::ExtTooLarge(len)) if len == too_long));
+    }
+
+    #[rstest]
+    #[case("table".to_string(), 1, 0)]
+    #[case("table".to_string(), 1, 130)]
+    #[case("a.much.longer.namespace.name".to_string(), 300, 5)]
+    #[case("n".repeat(32), 70000, 5)] // str8 namespace
+    #[case("n".repeat(300), 7, 5)] // str16 namespace
+    #[case("ns".to_string(), u32::MAX, 70000)]
+    fn test_namespace_encoded_value_ext_len(
+        #[case] namespace: String,
+        #[case] id: u32,
+        #[case] value_len: usize,
+    ) {
+        let namespace: &'static str = Box::leak(namespace.into_boxed_str());
+        let ns_value = NamespaceEncodedValue {
+            namespace,
+            id,
+            value: vec![0x55; value_len],
+        };
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        ns_value.to_bytes(buf).unwrap();
+
+        let mut rd = &buf[..];
+        let decoded = rmpv::decode::read_value(&mut rd).unwrap();
+        assert!(rd.is_empty(), "{} trailing bytes after ext", rd.len());
+
+        let (type_id, data) = decoded.as_ext().unwrap();
+        assert_eq!(type_id, CUSTOM_TYPE_EXT);
+        let mut pfx = Vec::new();
+        rmp::encode::write_str(&mut pfx, namespace).unwrap();
+        rmp::encode::write_sint(&mut pfx, id as i64).unwrap();
+        assert_eq!(data.len(), pfx.len() + value_len);
+        assert_eq!(&data[..pfx.len()], pfx.as_slice());
+        assert!(data[pfx.len()..].iter().all(|&byte| byte == 0x55));
+    }
+
+    #[cfg(feature = "indexmap")]
+    #[rstest]
+    fn test_encoding_indexmap_preserves_order() {
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        let mut map = indexmap::IndexMap::new();
+        map.insert(3, 4);
+        map.insert(1, 2);
+        map.to_bytes(buf).unwrap();
+
+        assert_eq!(buf.as_slice(), &[0b10000010, 0x03, 0x04, 0x01, 0x02]);
+    }
+
     #[cfg(feature = "derive")]
     mod derive_tests {
         use super::*;
-        use crate::ToBytesResult;
+        use crate::{Schema, SchemaField, ToBytesResult};
 
         #[derive(crate::ToBytesDict)]
         struct Person {
@@ -238,6 +784,13 @@ mod tests {
             data: Vec<u32>,
         }
 
+        #[derive(crate::ToBytesDict)]
+        #[tobytes(repr_int)]
+        enum Color {
+            Red = 1,
+            Green = 2,
+        }
+
         #[rstest]
         fn test_derive_named_struct() {
             let person = Person {
@@ -266,6 +819,43 @@ mod tests {
             }
         }
 
+        #[rstest]
+        fn test_derive_schema() {
+            assert_eq!(
+                Person::tobytes_schema(),
+                Schema {
+                    name: "Person",
+                    fields: vec![
+                        SchemaField {
+                            name: "name",
+                            type_name: "String",
+                        },
+                        SchemaField {
+                            name: "age",
+                            type_name: "u32",
+                        },
+                    ],
+                }
+            );
+            assert_eq!(Point::tobytes_schema().field("1").unwrap().type_name, "i32");
+        }
+
+        #[rstest]
+        fn test_derive_schema_type_names() {
+            #[derive(crate::ToBytesDict)]
+            struct Nested {
+                tags: std::collections::HashMap<String, Vec<u8>>,
+                raw: &'static [u8; 4],
+            }
+
+            let schema = Nested::tobytes_schema();
+            assert_eq!(
+                schema.field("tags").unwrap().type_name,
+                "std::collections::HashMap<String, Vec<u8>>"
+            );
+            assert_eq!(schema.field("raw").unwrap().type_name, "&'static [u8; 4]");
+        }
+
         #[rstest]
         fn test_derive_tuple_struct() {
             let point = Point(10, 20);
@@ -308,5 +898,15 @@ mod tests {
                 panic!("Expected a map");
             }
         }
+
+        #[rstest]
+        fn test_derive_repr_int_enum() {
+            let buf: &mut Vec<u8> = &mut Vec::new();
+            Color::Red.to_bytes(buf).unwrap();
+            Color::Green.to_bytes(buf).unwrap();
+
+            // Each variant encodes as its discriminant
+            assert_eq!(buf.as_slice(), &[0x01, 0x02]);
+        }
     }
 }
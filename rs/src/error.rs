@@ -11,6 +11,21 @@ pub enum Error {
     #[error("ndarray-npy error: {0}")]
     Npy(#[from] ndarray_npy::WriteNpyError),
 
+    #[error("npy shape mismatch: expected {expected} dimensions, found {found}")]
+    ShapeMismatch { expected: usize, found: usize },
+
+    #[error("Invalid npy header: {0}")]
+    InvalidNpyHeader(&'static str),
+
+    #[error("Unsupported npy format version {0}")]
+    UnsupportedNpyVersion(u8),
+
+    #[error("Unsupported npy dtype {0}")]
+    UnsupportedNpyDtype(String),
+
+    #[error("npy dtype mismatch: expected {expected}, found {found}")]
+    DtypeMismatch { expected: String, found: String },
+
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
@@ -18,17 +33,145 @@ pub enum Error {
     #[error("polars error: {0}")]
     Polars(#[from] polars::error::PolarsError),
 
+    #[cfg(feature = "serde")]
+    #[error("serde encode error: {0}")]
+    SerdeEncode(#[from] rmp_serde::encode::Error),
+
+    #[cfg(feature = "serde")]
+    #[error("serde decode error: {0}")]
+    SerdeDecode(#[from] rmp_serde::decode::Error),
+
     #[error("msgpack decode error: {0}")]
-    Decode(#[from] rmp::decode::ValueReadError),
+    Decode(rmp::decode::ValueReadError),
 
     #[error("msgpack decode error: {0}")]
-    DecodeConvert(#[from] rmpv::decode::Error),
+    DecodeConvert(rmpv::decode::Error),
+
+    #[error("Unexpected end of input")]
+    UnexpectedEof,
 
     #[error("Unexpected value: {0:?}")]
     UnexpectedValue(rmpv::Value),
 
     #[error("Unexpected value: {0:?}")]
     UnexpectedValueRef(String),
+
+    #[error("Expected ext type {expected}, found {found}")]
+    WrongExtType { expected: i8, found: i8 },
+
+    #[error("Expected namespace '{expected}', found '{found}'")]
+    WrongNamespace { expected: String, found: String },
+
+    #[error("Expected type id {expected}, found {found}")]
+    WrongId { expected: i64, found: i64 },
+
+    #[error("Unsupported table type id {0}")]
+    UnsupportedTableType(u32),
+
+    #[error("Record batch column '{column}' has {found} rows, expected {expected}")]
+    RaggedRecordBatch {
+        column: String,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("Namespace name of {0} bytes exceeds the maximum length")]
+    NamespaceTooLong(u32),
+
+    #[error("Namespace name is not valid UTF-8: {0}")]
+    InvalidNamespaceName(std::string::FromUtf8Error),
+
+    #[error("Truncated custom type: missing {0}")]
+    TruncatedCustomType(&'static str),
+
+    #[error("Reserved msgpack marker 0x{0:02x}")]
+    ReservedMarker(u8),
+
+    #[error("Expected msgpack {expected:?}, found {found:?}")]
+    UnexpectedType {
+        expected: crate::decode::MsgpackFamily,
+        found: crate::decode::MsgpackFamily,
+    },
+
+    #[error("Expected {expected} bytes, got {found}")]
+    LengthMismatch { expected: usize, found: usize },
+
+    #[error("Packed bit vector needs a 4 byte bit count, got {0} bytes")]
+    TruncatedBitVec(usize),
+
+    #[error("Packed bit vector of {bits} bits needs {expected} bytes, found {found}")]
+    BitVecLength {
+        bits: usize,
+        expected: usize,
+        found: usize,
+    },
+
+    #[error("Expected a map, found {found}")]
+    ExpectedMap { found: &'static str },
+
+    #[error("Expected a string map key, found {0}")]
+    NonStringMapKey(rmpv::Value),
+
+    #[error("Custom type body of {0} bytes does not fit in an ext32 length")]
+    ExtTooLarge(usize),
+
+    #[error("{0} trailing bytes after the decoded value")]
+    TrailingBytes(usize),
+
+    #[error("Encoded output exceeds {0} bytes")]
+    OutputTooLarge(usize),
+
+    #[error("Invalid hex: {0}")]
+    InvalidHex(String),
+
+    #[error("Invalid timestamp: {0}")]
+    InvalidTimestamp(String),
+}
+
+impl Error {
+    pub fn expected_map(value: &rmpv::Value) -> Self {
+        let found = match value {
+            rmpv::Value::Nil => "nil",
+            rmpv::Value::Boolean(_) => "boolean",
+            rmpv::Value::Integer(_) => "integer",
+            rmpv::Value::F32(_) | rmpv::Value::F64(_) => "float",
+            rmpv::Value::String(_) => "string",
+            rmpv::Value::Binary(_) => "binary",
+            rmpv::Value::Array(_) => "array",
+            rmpv::Value::Map(_) => "map",
+            rmpv::Value::Ext(_, _) => "ext",
+        };
+        Error::ExpectedMap { found }
+    }
+
+    /// Maps a failed read of msgpack data, reporting an exhausted reader as
+    /// `UnexpectedEof`.
+    pub(crate) fn from_read(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+            _ => Error::Io(err),
+        }
+    }
+}
+
+impl From<rmp::decode::ValueReadError> for Error {
+    fn from(err: rmp::decode::ValueReadError) -> Self {
+        match err {
+            rmp::decode::ValueReadError::InvalidMarkerRead(err)
+            | rmp::decode::ValueReadError::InvalidDataRead(err) => Error::from_read(err),
+            other => Error::Decode(other),
+        }
+    }
+}
+
+impl From<rmpv::decode::Error> for Error {
+    fn from(err: rmpv::decode::Error) -> Self {
+        match err {
+            rmpv::decode::Error::InvalidMarkerRead(err)
+            | rmpv::decode::Error::InvalidDataRead(err) => Error::from_read(err),
+            other => Error::DecodeConvert(other),
+        }
+    }
 }
 
 impl From<rmpv::Value> for Error {
@@ -1,17 +1,42 @@
+// Lets derive-generated `::tobytes::` paths resolve inside this crate too.
+extern crate self as tobytes;
+
+pub mod debug;
 mod decode;
 mod encode;
 mod error;
+mod schema;
+#[cfg(feature = "serde")]
+mod serde_bridge;
 pub mod table_ns;
-use error::Error;
+#[cfg(feature = "tokio-codec")]
+mod tokio_codec;
+mod wrappers;
+pub use error::Error;
 
-pub use decode::FromBytes;
-pub use encode::{NamespaceEncodedValue, ToBytes};
-pub use table_ns::{FromTableNs, ToTableNs};
+pub use decode::{
+    from_hex, peek_ns_header, peek_type, read_ns_payload, read_ns_payload_by_id, Bytes, FromBytes,
+    MsgpackFamily, MsgpackType, NamespaceRef, NsHeader, Partial, MAX_NAMESPACE_LEN,
+};
+pub use encode::{encode_map_from_iter, to_hex, NamespaceEncodedValue, ToBytes};
+pub use schema::{Schema, SchemaField};
+#[cfg(feature = "serde")]
+pub use serde_bridge::{from_bytes_serde, to_bytes_serde};
+#[cfg(feature = "polars")]
+pub use table_ns::from_table_ns_chunked;
+pub use table_ns::{
+    decode_table, peek_table_meta, FromTableNs, NdArrayValue, NpyMeta, RecordBatch, TableMeta,
+    TableValue, ToTableNs,
+};
+#[cfg(feature = "tokio-codec")]
+pub use tokio_codec::TobytesCodec;
+pub use wrappers::{BitVec, Byte, ByteVec, OneOrMany, Pairs, RelativeInstant, Scalar, SortedMap};
 
 #[cfg(feature = "derive")]
 pub use tobytes_derive::{FromBytesDict, ToBytesDict};
 
 pub type ToBytesResult<T> = std::result::Result<T, Error>;
+pub type ToBytesError = Error;
 
 pub trait Namespace {
     fn name() -> &'static str;
@@ -19,8 +44,62 @@ pub trait Namespace {
 
 pub const CUSTOM_TYPE_EXT: i8 = 8;
 
+/// The msgpack-defined timestamp extension type.
+pub const TIMESTAMP_EXT: i8 = -1;
+
+/// Derive attribute misuse is rejected at compile time. `repr_int` and
+/// `tag` are enum-only and mutually exclusive:
+///
+/// ```
+/// use tobytes::prelude::*;
+///
+/// #[derive(ToBytesDict, FromBytesDict)]
+/// #[tobytes(repr_int)]
+/// enum Level { Low, High }
+///
+/// #[derive(ToBytesDict, FromBytesDict)]
+/// #[tobytes(tag = "array")]
+/// enum Shape { Circle(u32), Square(u32) }
+/// ```
+///
+/// ```compile_fail
+/// use tobytes::prelude::*;
+///
+/// #[derive(ToBytesDict)]
+/// #[tobytes(repr_int)]
+/// struct Point { x: u32 }
+/// ```
+///
+/// ```compile_fail
+/// use tobytes::prelude::*;
+///
+/// #[derive(FromBytesDict)]
+/// #[tobytes(tag = "array")]
+/// struct Point { x: u32 }
+/// ```
+///
+/// ```compile_fail
+/// use tobytes::prelude::*;
+///
+/// #[derive(ToBytesDict)]
+/// #[tobytes(repr_int, tag = "array")]
+/// enum Level { Low, High }
+/// ```
+#[cfg(all(doctest, feature = "derive"))]
+pub struct DeriveAttributeErrors;
+
+/// Dependencies used by `macro_rules!` expansions, so callers don't need
+/// them as direct dependencies.
+#[doc(hidden)]
+pub mod __private {
+    pub use rmpv;
+}
+
 pub mod prelude {
-    pub use crate::{FromBytes, Namespace, NamespaceEncodedValue, ToBytes, ToBytesResult};
+    pub use crate::{
+        FromBytes, Namespace, NamespaceEncodedValue, Schema, SchemaField, ToBytes, ToBytesError,
+        ToBytesResult,
+    };
     #[cfg(feature = "derive")]
     pub use crate::{FromBytesDict, ToBytesDict};
     pub use crate::{FromTableNs, ToTableNs};
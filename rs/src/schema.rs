@@ -0,0 +1,20 @@
+/// Field layout of a struct deriving `ToBytesDict`, as returned by its
+/// generated `tobytes_schema()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schema {
+    pub name: &'static str,
+    pub fields: Vec<SchemaField>,
+}
+
+/// A single struct field. Tuple struct fields are named by their index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaField {
+    pub name: &'static str,
+    pub type_name: &'static str,
+}
+
+impl Schema {
+    pub fn field(&self, name: &str) -> Option<&SchemaField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+}
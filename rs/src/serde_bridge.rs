@@ -0,0 +1,97 @@
+use crate::ToBytesResult;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::io::{Read, Write};
+
+/// Decodes a `serde::Deserialize` type with `rmp-serde`. Structs may be
+/// encoded either as maps, as `ToBytesDict` writes them, or as arrays.
+pub fn from_bytes_serde<T: DeserializeOwned, R: Read>(rd: R) -> ToBytesResult<T> {
+    Ok(rmp_serde::from_read(rd)?)
+}
+
+/// Encodes a `serde::Serialize` type with `rmp-serde`. Structs are written
+/// as maps keyed by field name, so they decode with `FromBytesDict`.
+pub fn to_bytes_serde<T: Serialize + ?Sized, W: Write>(value: &T, wr: &mut W) -> ToBytesResult<()> {
+    rmp_serde::encode::write_named(wr, value)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[cfg(feature = "derive")]
+    #[rstest]
+    fn test_from_bytes_serde_matches_derive() {
+        use crate::prelude::*;
+
+        #[derive(crate::ToBytesDict, crate::FromBytesDict, Debug, PartialEq)]
+        struct Person {
+            name: String,
+            age: u32,
+        }
+
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct SerdePerson {
+            name: String,
+            age: u32,
+        }
+
+        let person = Person {
+            name: "Alice".to_string(),
+            age: 30,
+        };
+        let mut buf = Vec::new();
+        person.to_bytes(&mut buf).unwrap();
+
+        let decoded: SerdePerson = from_bytes_serde(&buf[..]).unwrap();
+        let native = Person::from_bytes(&mut &buf[..]).unwrap();
+        assert_eq!(decoded.name, native.name);
+        assert_eq!(decoded.age, native.age);
+    }
+
+    #[cfg(feature = "derive")]
+    #[rstest]
+    fn test_to_bytes_serde_decodes_with_derive() {
+        use crate::prelude::*;
+
+        #[derive(serde::Serialize)]
+        struct SerdePerson {
+            name: String,
+            age: u32,
+            tags: Vec<String>,
+        }
+
+        #[derive(crate::FromBytesDict, Debug, PartialEq)]
+        struct Person {
+            name: String,
+            age: u32,
+            tags: Vec<String>,
+        }
+
+        let person = SerdePerson {
+            name: "Bob".to_string(),
+            age: 42,
+            tags: vec!["a".to_string()],
+        };
+        let mut buf = Vec::new();
+        to_bytes_serde(&person, &mut buf).unwrap();
+
+        let decoded = Person::from_bytes(&mut &buf[..]).unwrap();
+        assert_eq!(
+            decoded,
+            Person {
+                name: "Bob".to_string(),
+                age: 42,
+                tags: vec!["a".to_string()],
+            }
+        );
+    }
+
+    #[rstest]
+    fn test_from_bytes_serde_error() {
+        let buf = [0xa1, b'x'];
+        assert!(from_bytes_serde::<u32, _>(&buf[..]).is_err());
+    }
+}
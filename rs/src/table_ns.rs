@@ -1,52 +1,91 @@
-use crate::decode::read_ns_payload;
+use crate::decode::{peek_ns_header, read_ns_payload, read_payload, NamespaceRef, NsHeader};
+use crate::error::Error;
 use crate::{Namespace, NamespaceEncodedValue, ToBytesResult};
 use ndarray::{Data, Dimension};
-use ndarray_npy::{ReadNpyExt, WriteNpyExt};
+use ndarray_npy::{ReadNpyError, ReadNpyExt, WritableElement, WriteNpyExt};
 use std::io::Read;
 
 #[cfg(feature = "polars")]
 use polars::io::parquet::{ParquetReader, ParquetWriter};
 #[cfg(feature = "polars")]
 use polars::prelude::{DataFrame as PolarsDataFrame, SerReader};
+#[cfg(feature = "polars")]
+use polars_parquet::read as parquet_read;
 
 pub trait ToTableNs {
     fn to_table_ns(&self) -> ToBytesResult<NamespaceEncodedValue>;
+
+    /// Like `to_table_ns`, but writes arrays in C (row-major) order whatever
+    /// their memory layout. Types without a layout encode as usual.
+    fn to_table_ns_c_order(&self) -> ToBytesResult<NamespaceEncodedValue> {
+        self.to_table_ns()
+    }
 }
 
 pub trait FromTableNs: Sized {
     fn from_table_ns<R: Read>(rd: &mut R) -> ToBytesResult<Self>;
 }
 
+fn npy_table_ns<A: WriteNpyExt>(array: &A) -> ToBytesResult<NamespaceEncodedValue> {
+    let buf = Vec::new();
+    let mut wr = std::io::Cursor::new(buf);
+    array.write_npy(&mut wr)?;
+    Ok(NamespaceEncodedValue {
+        namespace: "table",
+        id: 1,
+        value: wr.into_inner(),
+    })
+}
+
 impl<S, D> ToTableNs for ndarray::ArrayBase<S, D>
 where
     S: Data,
+    S::Elem: WritableElement + Clone,
     D: Dimension,
-    ndarray::ArrayBase<S, D>: WriteNpyExt,
 {
     fn to_table_ns(&self) -> ToBytesResult<NamespaceEncodedValue> {
-        let buf = Vec::new();
-        let mut wr = std::io::Cursor::new(buf);
-        self.write_npy(&mut wr)?;
-        Ok(NamespaceEncodedValue {
-            namespace: "table",
-            id: 1,
-            value: wr.into_inner(),
-        })
+        npy_table_ns(self)
+    }
+
+    fn to_table_ns_c_order(&self) -> ToBytesResult<NamespaceEncodedValue> {
+        npy_table_ns(&self.as_standard_layout())
     }
 }
 
 impl<S, D> FromTableNs for ndarray::ArrayBase<S, D>
 where
     S: Data,
+    S::Elem: WritableElement,
     D: Dimension,
     ndarray::ArrayBase<S, D>: ReadNpyExt,
 {
     fn from_table_ns<R: std::io::Read>(rd: &mut R) -> ToBytesResult<Self> {
         let payload = read_ns_payload(rd, "table", 1)?;
-        Ok(Self::read_npy(&mut std::io::Cursor::new(payload))?)
+        Self::read_npy(&payload[..]).map_err(|err| match err {
+            ReadNpyError::WrongNdim(Some(expected), found) => {
+                Error::ShapeMismatch { expected, found }
+            }
+            ReadNpyError::WrongDescriptor(_) => {
+                match (npy_descr::<S::Elem>(), NpyMeta::read(&mut &payload[..])) {
+                    (Ok(expected), Ok(meta)) => Error::DtypeMismatch {
+                        expected,
+                        found: meta.dtype,
+                    },
+                    _ => err.into(),
+                }
+            }
+            err => err.into(),
+        })
     }
 }
 
+/// The npy dtype descriptor (e.g. `<f8`) that `T` is written with.
+fn npy_descr<T: WritableElement>() -> ToBytesResult<String> {
+    let mut buf = Vec::new();
+    ndarray::Array1::<T>::from_vec(Vec::new()).write_npy(&mut buf)?;
+    Ok(NpyMeta::read(&mut &buf[..])?.dtype)
+}
+
 #[cfg(feature = "polars")]
 impl ToTableNs for PolarsDataFrame {
     fn to_table_ns(&self) -> ToBytesResult<NamespaceEncodedValue> {
@@ -74,6 +113,271 @@ impl FromTableNs for PolarsDataFrame {
     }
 }
 
+/// Decodes a parquet table payload one row group at a time, so only a single
+/// group's frame is materialised at once.
+#[cfg(feature = "polars")]
+pub fn from_table_ns_chunked<R: Read>(
+    rd: &mut R,
+) -> ToBytesResult<impl Iterator<Item = ToBytesResult<PolarsDataFrame>>> {
+    let payload = read_ns_payload(rd, "table", 3)?;
+    let mut cursor = std::io::Cursor::new(payload);
+    let metadata = parquet_read::read_metadata(&mut cursor)?;
+    let schema = parquet_read::infer_schema(&metadata)?;
+    let fields = schema.fields.clone();
+    let row_groups =
+        parquet_read::FileReader::new(cursor, metadata.row_groups, schema, None, None, None);
+    Ok(row_groups.map(move |batch| Ok(PolarsDataFrame::try_from((batch?, fields.as_slice()))?)))
+}
+
+/// A lightweight column-oriented table, for tabular interop without the
+/// `polars` dependency. Encodes as a msgpack map of column name to value
+/// array under table id 4, matching Python's `tobytes.table.RecordBatch`.
+/// All columns must have the same length.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordBatch {
+    pub columns: Vec<(String, Vec<rmpv::Value>)>,
+}
+
+impl RecordBatch {
+    pub fn num_rows(&self) -> usize {
+        self.columns.first().map_or(0, |(_, values)| values.len())
+    }
+
+    fn check_lengths(&self) -> ToBytesResult<()> {
+        let rows = self.num_rows();
+        match self.columns.iter().find(|(_, values)| values.len() != rows) {
+            Some((name, values)) => Err(Error::RaggedRecordBatch {
+                column: name.clone(),
+                expected: rows,
+                found: values.len(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn from_payload(payload: &[u8]) -> ToBytesResult<Self> {
+        let pairs = Vec::<(rmpv::Value, rmpv::Value)>::try_from(rmpv::decode::read_value(
+            &mut &payload[..],
+        )?)?;
+
+        let mut columns = Vec::with_capacity(pairs.len());
+        for (name, values) in pairs {
+            columns.push((
+                String::try_from(name)?,
+                Vec::<rmpv::Value>::try_from(values)?,
+            ));
+        }
+        let batch = RecordBatch { columns };
+        batch.check_lengths()?;
+        Ok(batch)
+    }
+}
+
+impl ToTableNs for RecordBatch {
+    fn to_table_ns(&self) -> ToBytesResult<NamespaceEncodedValue> {
+        self.check_lengths()?;
+        let mut buffer = Vec::new();
+        rmp::encode::write_map_len(&mut buffer, self.columns.len() as u32)?;
+        for (name, values) in &self.columns {
+            rmp::encode::write_str(&mut buffer, name)?;
+            rmp::encode::write_array_len(&mut buffer, values.len() as u32)?;
+            for value in values {
+                rmpv::encode::write_value(&mut buffer, value)?;
+            }
+        }
+
+        Ok(NamespaceEncodedValue {
+            namespace: "table",
+            id: 4,
+            value: buffer,
+        })
+    }
+}
+
+impl FromTableNs for RecordBatch {
+    fn from_table_ns<R: std::io::Read>(rd: &mut R) -> ToBytesResult<Self> {
+        let payload = read_ns_payload(rd, "table", 4)?;
+        Self::from_payload(&payload)
+    }
+}
+
+/// A dynamically-dimensioned ndarray of any element type npy payloads can
+/// hold.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NdArrayValue {
+    Bool(ndarray::ArrayD<bool>),
+    I8(ndarray::ArrayD<i8>),
+    I16(ndarray::ArrayD<i16>),
+    I32(ndarray::ArrayD<i32>),
+    I64(ndarray::ArrayD<i64>),
+    U8(ndarray::ArrayD<u8>),
+    U16(ndarray::ArrayD<u16>),
+    U32(ndarray::ArrayD<u32>),
+    U64(ndarray::ArrayD<u64>),
+    F32(ndarray::ArrayD<f32>),
+    F64(ndarray::ArrayD<f64>),
+}
+
+impl NdArrayValue {
+    fn from_npy(payload: &[u8]) -> ToBytesResult<Self> {
+        let meta = NpyMeta::read(&mut &payload[..])?;
+        macro_rules! read {
+            ($variant:ident) => {
+                NdArrayValue::$variant(ndarray::ArrayD::read_npy(payload)?)
+            };
+        }
+        // The byte order prefix is handled by `read_npy`.
+        Ok(match meta.dtype.trim_start_matches(['<', '>', '|', '=']) {
+            "b1" => read!(Bool),
+            "i1" => read!(I8),
+            "i2" => read!(I16),
+            "i4" => read!(I32),
+            "i8" => read!(I64),
+            "u1" => read!(U8),
+            "u2" => read!(U16),
+            "u4" => read!(U32),
+            "u8" => read!(U64),
+            "f4" => read!(F32),
+            "f8" => read!(F64),
+            _ => return Err(Error::UnsupportedNpyDtype(meta.dtype)),
+        })
+    }
+}
+
+/// Any value from the table namespace, as returned by [`decode_table`].
+#[derive(Debug)]
+pub enum TableValue {
+    NdArray(NdArrayValue),
+    RecordBatch(RecordBatch),
+    #[cfg(feature = "polars")]
+    DataFrame(PolarsDataFrame),
+}
+
+/// Decodes a table namespace value without knowing its concrete type ahead
+/// of time, dispatching on the type id in the custom type header.
+pub fn decode_table<R: Read>(rd: &mut R) -> ToBytesResult<TableValue> {
+    let header = read_table_header(rd)?;
+    let payload = read_payload(rd, &header)?;
+    match header.type_id {
+        1 => Ok(TableValue::NdArray(NdArrayValue::from_npy(&payload)?)),
+        // pandas (2) and polars (3) frames are both written as parquet.
+        #[cfg(feature = "polars")]
+        2 | 3 => Ok(TableValue::DataFrame(
+            ParquetReader::new(std::io::Cursor::new(payload)).finish()?,
+        )),
+        4 => Ok(TableValue::RecordBatch(RecordBatch::from_payload(
+            &payload,
+        )?)),
+        type_id => Err(Error::UnsupportedTableType(type_id)),
+    }
+}
+
+fn read_table_header<R: Read>(rd: &mut R) -> ToBytesResult<NsHeader> {
+    let header = peek_ns_header(rd)?;
+    if header.namespace != NamespaceRef::name("table") {
+        return Err(Error::WrongNamespace {
+            expected: "table".to_string(),
+            found: header.namespace.to_string(),
+        });
+    }
+    Ok(header)
+}
+
+/// Header of an npy payload: its dtype descriptor (e.g. `<f8`), shape and
+/// memory order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NpyMeta {
+    pub dtype: String,
+    pub shape: Vec<usize>,
+    pub fortran_order: bool,
+}
+
+impl NpyMeta {
+    fn read<R: Read>(rd: &mut R) -> ToBytesResult<Self> {
+        let mut preamble = [0u8; 8];
+        rd.read_exact(&mut preamble).map_err(Error::from_read)?;
+        if &preamble[..6] != b"\x93NUMPY" {
+            return Err(Error::InvalidNpyHeader("bad magic"));
+        }
+        let header_len = match preamble[6] {
+            1 => {
+                let mut len = [0u8; 2];
+                rd.read_exact(&mut len).map_err(Error::from_read)?;
+                u16::from_le_bytes(len) as usize
+            }
+            2 | 3 => {
+                let mut len = [0u8; 4];
+                rd.read_exact(&mut len).map_err(Error::from_read)?;
+                u32::from_le_bytes(len) as usize
+            }
+            version => return Err(Error::UnsupportedNpyVersion(version)),
+        };
+        let mut header = Vec::new();
+        rd.take(header_len as u64).read_to_end(&mut header)?;
+        if header.len() != header_len {
+            return Err(Error::UnexpectedEof);
+        }
+        let header =
+            String::from_utf8(header).map_err(|_| Error::InvalidNpyHeader("not valid UTF-8"))?;
+
+        let field = |key: &str| {
+            let start = header.find(&format!("'{}':", key))?;
+            Some(header[start + key.len() + 3..].trim_start())
+        };
+        let dtype = field("descr")
+            .ok_or(Error::InvalidNpyHeader("missing 'descr'"))?
+            .strip_prefix('\'')
+            .and_then(|rest| rest.split('\'').next())
+            .ok_or(Error::InvalidNpyHeader("descr is not a string"))?
+            .to_string();
+        let fortran_order = field("fortran_order")
+            .ok_or(Error::InvalidNpyHeader("missing 'fortran_order'"))?
+            .starts_with("True");
+        let not_a_tuple = || Error::InvalidNpyHeader("shape is not a tuple");
+        let shape = field("shape")
+            .ok_or(Error::InvalidNpyHeader("missing 'shape'"))?
+            .strip_prefix('(')
+            .and_then(|rest| rest.split(')').next())
+            .ok_or_else(not_a_tuple)?
+            .split(',')
+            .map(str::trim)
+            .filter(|dim| !dim.is_empty())
+            .map(|dim| dim.parse().map_err(|_| not_a_tuple()))
+            .collect::<ToBytesResult<Vec<usize>>>()?;
+        Ok(NpyMeta {
+            dtype,
+            shape,
+            fortran_order,
+        })
+    }
+}
+
+/// Table metadata read by [`peek_table_meta`]. `npy` is set for ndarray
+/// payloads.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableMeta {
+    pub type_id: u32,
+    pub payload_len: usize,
+    pub npy: Option<NpyMeta>,
+}
+
+/// Reads a table value's custom type header and, for npy payloads, the npy
+/// header, without decoding the data. The reader is left partway through
+/// the payload.
+pub fn peek_table_meta<R: Read>(rd: &mut R) -> ToBytesResult<TableMeta> {
+    let header = read_table_header(rd)?;
+    let npy = match header.type_id {
+        1 => Some(NpyMeta::read(&mut rd.take(header.payload_len as u64))?),
+        _ => None,
+    };
+    Ok(TableMeta {
+        type_id: header.type_id,
+        payload_len: header.payload_len,
+        npy,
+    })
+}
+
+#[allow(dead_code)]
 struct TableNamespace;
 
 impl Namespace for TableNamespace {
@@ -90,7 +394,7 @@ mod tests {
     use crate::encode::ToBytes;
 
     #[cfg(feature = "polars")]
-    use polars::prelude::{DataFrame as TestDataFrame, Series};
+    use polars::prelude::{DataFrame as TestDataFrame, NamedFrom, Series};
 
     #[rstest]
     fn test_table_namespace_encoding() {
@@ -129,7 +433,7 @@ mod tests {
         let value = ndarray::array![[10u8, 20u8], [30u8, 40u8]];
         let ns_value = value.to_table_ns().unwrap();
 
-        let mut buf: &mut Vec<u8> = &mut Vec::new();
+        let buf: &mut Vec<u8> = &mut Vec::new();
         ns_value.to_bytes(buf).unwrap();
 
         let decoded_value: ndarray::Array2<u8> =
@@ -137,6 +441,330 @@ mod tests {
         assert_eq!(value, decoded_value);
     }
 
+    #[rstest]
+    fn test_table_shape_mismatch() {
+        let value = ndarray::array![[10u8, 20u8], [30u8, 40u8]];
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        value.to_table_ns().unwrap().to_bytes(buf).unwrap();
+
+        let err =
+            <ndarray::Array1<u8> as FromTableNs>::from_table_ns(&mut std::io::Cursor::new(buf))
+                .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::ShapeMismatch {
+                expected: 1,
+                found: 2
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "npy shape mismatch: expected 1 dimensions, found 2"
+        );
+    }
+
+    #[rstest]
+    fn test_table_dtype_mismatch() {
+        let value = ndarray::array![[1.5f64, 2.5], [3.5, 4.5]];
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        value.to_table_ns().unwrap().to_bytes(buf).unwrap();
+
+        let err =
+            <ndarray::Array2<u8> as FromTableNs>::from_table_ns(&mut std::io::Cursor::new(buf))
+                .unwrap_err();
+        assert!(matches!(&err, Error::DtypeMismatch { expected, found }
+            if expected == "|u1" && found == "<f8"));
+        assert_eq!(
+            err.to_string(),
+            "npy dtype mismatch: expected |u1, found <f8"
+        );
+    }
+
+    #[rstest]
+    fn test_record_batch_round_trip() {
+        let batch = RecordBatch {
+            columns: vec![
+                (
+                    "id".to_string(),
+                    vec![rmpv::Value::from(1), rmpv::Value::from(2)],
+                ),
+                (
+                    "name".to_string(),
+                    vec![rmpv::Value::from("a"), rmpv::Value::Nil],
+                ),
+            ],
+        };
+        assert_eq!(batch.num_rows(), 2);
+
+        let ns_value = batch.to_table_ns().unwrap();
+        assert_eq!(ns_value.namespace, "table");
+        assert_eq!(ns_value.id, 4);
+        assert_eq!(ns_value.value[0], 0b10000010); // fixmap with 2 columns
+
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        ns_value.to_bytes(buf).unwrap();
+
+        let decoded: RecordBatch =
+            FromTableNs::from_table_ns(&mut std::io::Cursor::new(buf)).unwrap();
+        assert_eq!(decoded, batch);
+    }
+
+    #[rstest]
+    fn test_record_batch_ragged() {
+        let batch = RecordBatch {
+            columns: vec![
+                (
+                    "id".to_string(),
+                    vec![rmpv::Value::from(1), rmpv::Value::from(2)],
+                ),
+                ("name".to_string(), vec![rmpv::Value::from("a")]),
+            ],
+        };
+        let Err(err) = batch.to_table_ns() else {
+            panic!("Expected a ragged batch to fail to encode");
+        };
+        assert!(
+            matches!(&err, Error::RaggedRecordBatch { column, expected: 2, found: 1 }
+            if column == "name")
+        );
+
+        let payload = rmpv::Value::Map(
+            batch
+                .columns
+                .into_iter()
+                .map(|(name, values)| (rmpv::Value::from(name), rmpv::Value::Array(values)))
+                .collect(),
+        );
+        let mut value = Vec::new();
+        rmpv::encode::write_value(&mut value, &payload).unwrap();
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        NamespaceEncodedValue {
+            namespace: "table",
+            id: 4,
+            value,
+        }
+        .to_bytes(buf)
+        .unwrap();
+        let err = <RecordBatch as FromTableNs>::from_table_ns(&mut &buf[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            Error::RaggedRecordBatch {
+                expected: 2,
+                found: 1,
+                ..
+            }
+        ));
+    }
+
+    #[rstest]
+    fn test_decode_table_ndarray() {
+        let value = ndarray::array![[1.5f64, 2.5], [3.5, 4.5]];
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        value.to_table_ns().unwrap().to_bytes(buf).unwrap();
+
+        match decode_table(&mut &buf[..]).unwrap() {
+            TableValue::NdArray(NdArrayValue::F64(array)) => {
+                assert_eq!(array, value.into_dyn());
+            }
+            other => panic!("Expected an f64 ndarray, got {:?}", other),
+        }
+    }
+
+    fn decode_table_round_trip(value: &impl ToTableNs) -> TableValue {
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        value.to_table_ns().unwrap().to_bytes(buf).unwrap();
+        decode_table(&mut &buf[..]).unwrap()
+    }
+
+    #[rstest]
+    fn test_decode_table_one_byte_dtypes() {
+        let bools = ndarray::array![true, false];
+        let bytes = ndarray::array![1u8, 2];
+        let signed = ndarray::array![-1i8, 2];
+        assert!(matches!(
+            decode_table_round_trip(&bools),
+            TableValue::NdArray(NdArrayValue::Bool(array)) if array == bools.into_dyn()
+        ));
+        assert!(matches!(
+            decode_table_round_trip(&bytes),
+            TableValue::NdArray(NdArrayValue::U8(array)) if array == bytes.into_dyn()
+        ));
+        assert!(matches!(
+            decode_table_round_trip(&signed),
+            TableValue::NdArray(NdArrayValue::I8(array)) if array == signed.into_dyn()
+        ));
+    }
+
+    #[rstest]
+    fn test_decode_table_unsupported_dtype() {
+        let value = ndarray::array![1.5f64, 2.5];
+        let mut payload = value.to_table_ns().unwrap().value;
+        let descr = payload.windows(4).position(|w| w == b"<f8'").unwrap();
+        payload[descr..descr + 3].copy_from_slice(b"<c8");
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        NamespaceEncodedValue {
+            namespace: "table",
+            id: 1,
+            value: payload,
+        }
+        .to_bytes(buf)
+        .unwrap();
+
+        let err = decode_table(&mut &buf[..]).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedNpyDtype(dtype) if dtype == "<c8"));
+    }
+
+    #[rstest]
+    fn test_peek_table_meta_ndarray() {
+        let value = ndarray::Array3::<f32>::zeros((2, 3, 4));
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        value.to_table_ns().unwrap().to_bytes(buf).unwrap();
+
+        let meta = peek_table_meta(&mut &buf[..]).unwrap();
+        assert_eq!(meta.type_id, 1);
+        assert_eq!(
+            meta.npy,
+            Some(NpyMeta {
+                dtype: "<f4".to_string(),
+                shape: vec![2, 3, 4],
+                fortran_order: false,
+            })
+        );
+    }
+
+    #[rstest]
+    #[case(b"\x93NUMPX\x01\x00\x00\x00".to_vec(), "bad magic")]
+    #[case(b"\x93NUMPY\x01\x00\x0b\x00{'descr': 1}".to_vec(), "descr is not a string")]
+    #[case(b"\x93NUMPY\x01\x00\x02\x00{}".to_vec(), "missing 'descr'")]
+    fn test_npy_meta_invalid_header(#[case] payload: Vec<u8>, #[case] reason: &str) {
+        let err = NpyMeta::read(&mut &payload[..]).unwrap_err();
+        assert!(matches!(err, Error::InvalidNpyHeader(found) if found == reason));
+    }
+
+    #[rstest]
+    #[case(b"\x93NUM".to_vec())]
+    #[case(b"\x93NUMPY\x01\x00\x10".to_vec())]
+    fn test_npy_meta_truncated(#[case] payload: Vec<u8>) {
+        let err = NpyMeta::read(&mut &payload[..]).unwrap_err();
+        assert!(matches!(err, Error::UnexpectedEof));
+    }
+
+    #[rstest]
+    fn test_npy_meta_unsupported_version() {
+        let payload = b"\x93NUMPY\x09\x00";
+        let err = NpyMeta::read(&mut &payload[..]).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedNpyVersion(9)));
+    }
+
+    #[rstest]
+    fn test_table_c_order() {
+        use ndarray::ShapeBuilder;
+
+        let value = ndarray::Array2::from_shape_vec((2, 3).f(), vec![1i32, 4, 2, 5, 3, 6]).unwrap();
+        assert_eq!(value, ndarray::array![[1, 2, 3], [4, 5, 6]]);
+
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        value.to_table_ns().unwrap().to_bytes(buf).unwrap();
+        let meta = peek_table_meta(&mut &buf[..]).unwrap();
+        assert!(meta.npy.unwrap().fortran_order);
+
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        value.to_table_ns_c_order().unwrap().to_bytes(buf).unwrap();
+        let meta = peek_table_meta(&mut &buf[..]).unwrap();
+        assert!(!meta.npy.unwrap().fortran_order);
+
+        let decoded: ndarray::Array2<i32> =
+            FromTableNs::from_table_ns(&mut std::io::Cursor::new(&buf[..])).unwrap();
+        assert_eq!(decoded, value);
+        assert!(decoded.is_standard_layout());
+    }
+
+    #[rstest]
+    fn test_peek_table_meta_record_batch() {
+        let batch = RecordBatch {
+            columns: vec![("a".to_string(), vec![rmpv::Value::from(1)])],
+        };
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        batch.to_table_ns().unwrap().to_bytes(buf).unwrap();
+
+        let meta = peek_table_meta(&mut &buf[..]).unwrap();
+        assert_eq!(meta.type_id, 4);
+        assert_eq!(meta.npy, None);
+    }
+
+    #[rstest]
+    fn test_decode_table_record_batch() {
+        let batch = RecordBatch {
+            columns: vec![("x".to_string(), vec![rmpv::Value::from(1)])],
+        };
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        batch.to_table_ns().unwrap().to_bytes(buf).unwrap();
+
+        match decode_table(&mut &buf[..]).unwrap() {
+            TableValue::RecordBatch(decoded) => assert_eq!(decoded, batch),
+            other => panic!("Expected a record batch, got {:?}", other),
+        }
+    }
+
+    #[rstest]
+    fn test_decode_table_unsupported_type() {
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        NamespaceEncodedValue {
+            namespace: "table",
+            id: 99,
+            value: vec![1, 2, 3],
+        }
+        .to_bytes(buf)
+        .unwrap();
+
+        let err = decode_table(&mut &buf[..]).unwrap_err();
+        assert!(matches!(err, Error::UnsupportedTableType(99)));
+    }
+
+    #[rstest]
+    fn test_decode_table_payload_length_not_trusted() {
+        let mut buf = vec![];
+        rmp::encode::write_ext_meta(&mut buf, u32::MAX, crate::CUSTOM_TYPE_EXT).unwrap();
+        rmp::encode::write_str(&mut buf, "table").unwrap();
+        rmp::encode::write_sint(&mut buf, 1).unwrap();
+        buf.extend_from_slice(&[1, 2, 3]);
+
+        let err = decode_table(&mut &buf[..]).unwrap_err();
+        assert!(matches!(err, Error::UnexpectedEof));
+    }
+
+    #[cfg(feature = "polars")]
+    #[rstest]
+    fn test_decode_table_pandas_dataframe() {
+        let df = TestDataFrame::new(vec![Series::new("id", &[1i64, 2, 3])]).unwrap();
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        NamespaceEncodedValue {
+            namespace: "table",
+            id: 2,
+            value: df.to_table_ns().unwrap().value,
+        }
+        .to_bytes(buf)
+        .unwrap();
+
+        match decode_table(&mut &buf[..]).unwrap() {
+            TableValue::DataFrame(decoded) => assert!(decoded.equals(&df)),
+            other => panic!("Expected a dataframe, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "polars")]
+    #[rstest]
+    fn test_decode_table_dataframe() {
+        let df = TestDataFrame::new(vec![Series::new("id", &[1i64, 2, 3])]).unwrap();
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        df.to_table_ns().unwrap().to_bytes(buf).unwrap();
+
+        match decode_table(&mut &buf[..]).unwrap() {
+            TableValue::DataFrame(decoded) => assert!(decoded.equals(&df)),
+            other => panic!("Expected a dataframe, got {:?}", other),
+        }
+    }
+
     #[cfg(feature = "polars")]
     #[rstest]
     fn test_polars_table_round_trip() {
@@ -150,11 +778,47 @@ mod tests {
         assert_eq!(ns_value.namespace, "table");
         assert_eq!(ns_value.id, 3);
 
-        let mut buf: &mut Vec<u8> = &mut Vec::new();
+        let buf: &mut Vec<u8> = &mut Vec::new();
         ns_value.to_bytes(buf).unwrap();
 
         let decoded: TestDataFrame =
             FromTableNs::from_table_ns(&mut std::io::Cursor::new(buf)).unwrap();
-        assert!(decoded.frame_equal(&df));
+        assert!(decoded.equals(&df));
+    }
+
+    #[cfg(feature = "polars")]
+    #[rstest]
+    fn test_polars_chunked_decode() {
+        let df = TestDataFrame::new(vec![
+            Series::new("id", &[1i64, 2, 3, 4, 5]),
+            Series::new("value", &["a", "b", "c", "d", "e"]),
+        ])
+        .unwrap();
+        let mut payload = Vec::new();
+        ParquetWriter::new(&mut payload)
+            .with_row_group_size(Some(2))
+            .finish(&mut df.clone())
+            .unwrap();
+        let buf: &mut Vec<u8> = &mut Vec::new();
+        NamespaceEncodedValue {
+            namespace: "table",
+            id: 3,
+            value: payload,
+        }
+        .to_bytes(buf)
+        .unwrap();
+
+        let chunks = from_table_ns_chunked(&mut &buf[..])
+            .unwrap()
+            .collect::<ToBytesResult<Vec<_>>>()
+            .unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.height() < df.height()));
+
+        let mut joined = chunks[0].clone();
+        for chunk in &chunks[1..] {
+            joined.vstack_mut(chunk).unwrap();
+        }
+        assert!(joined.equals(&df));
     }
 }
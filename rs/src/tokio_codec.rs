@@ -0,0 +1,128 @@
+use crate::error::Error;
+use crate::{FromBytes, ToBytes};
+use bytes::BytesMut;
+use std::marker::PhantomData;
+use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
+
+/// A `tokio_util` codec that frames each value as a big-endian `u32` length
+/// prefix followed by its tobytes encoding.
+///
+/// Decoding buffers partial reads until a complete frame is available, and
+/// rejects frames holding more than one encoded value.
+pub struct TobytesCodec<T> {
+    frames: LengthDelimitedCodec,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> TobytesCodec<T> {
+    pub fn new() -> Self {
+        Self {
+            frames: LengthDelimitedCodec::new(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Limits the size of a single frame; larger frames are rejected on both
+    /// encode and decode.
+    pub fn with_max_frame_length(mut self, len: usize) -> Self {
+        self.frames.set_max_frame_length(len);
+        self
+    }
+}
+
+impl<T> Default for TobytesCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: ToBytes> Encoder<T> for TobytesCodec<T> {
+    type Error = Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let mut payload = Vec::new();
+        item.to_bytes(&mut payload)?;
+        Ok(self.frames.encode(payload.into(), dst)?)
+    }
+}
+
+impl<T: FromBytes> Decoder for TobytesCodec<T> {
+    type Item = T::Output;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.frames.decode(src)? {
+            Some(frame) => {
+                let mut rd = &frame[..];
+                let item = T::from_bytes(&mut rd)?;
+                if !rd.is_empty() {
+                    return Err(Error::TrailingBytes(rd.len()));
+                }
+                Ok(Some(item))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use rstest::rstest;
+    use tokio_util::codec::{FramedRead, FramedWrite};
+
+    #[tokio::test]
+    async fn test_framed_duplex_round_trip() {
+        let (client, server) = tokio::io::duplex(16);
+        let mut writer = FramedWrite::new(client, TobytesCodec::<Vec<String>>::new());
+        let mut reader = FramedRead::new(server, TobytesCodec::<Vec<String>>::new());
+
+        let messages = vec![
+            vec!["hello".to_string()],
+            vec![],
+            vec!["a much longer message than the duplex buffer".to_string(); 3],
+        ];
+
+        let to_send = messages.clone();
+        let send = tokio::spawn(async move {
+            for message in to_send {
+                writer.send(message).await.unwrap();
+            }
+        });
+
+        for expected in messages {
+            let received = reader.next().await.unwrap().unwrap();
+            assert_eq!(received, expected);
+        }
+        send.await.unwrap();
+        assert!(reader.next().await.is_none());
+    }
+
+    #[rstest]
+    fn test_decoder_waits_for_full_frame() {
+        let mut codec = TobytesCodec::<String>::new();
+        let mut encoded = BytesMut::new();
+        codec.encode("hello".to_string(), &mut encoded).unwrap();
+
+        let mut partial = encoded.split_to(3);
+        assert!(codec.decode(&mut partial).unwrap().is_none());
+
+        partial.unsplit(encoded);
+        assert_eq!(codec.decode(&mut partial).unwrap().unwrap(), "hello");
+    }
+
+    #[rstest]
+    fn test_decoder_rejects_trailing_bytes() {
+        let mut frames = LengthDelimitedCodec::new();
+        let mut encoded = BytesMut::new();
+        frames
+            .encode(vec![0xa2, b'h', b'i', 0xc0].into(), &mut encoded)
+            .unwrap();
+
+        let err = TobytesCodec::<String>::new()
+            .decode(&mut encoded)
+            .unwrap_err();
+        assert!(matches!(err, Error::TrailingBytes(1)));
+    }
+}
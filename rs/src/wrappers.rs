@@ -0,0 +1,529 @@
+//! Newtype wrappers that change how a value is laid out on the wire.
+
+use crate::error::Error;
+use crate::{FromBytes, ToBytes, ToBytesResult};
+use rmpv::decode::read_value;
+use rmpv::encode::write_value_ref;
+use std::io::Write;
+
+/// An `Instant` encoded as the time elapsed since a reference instant, as a
+/// `[secs, nanos]` array. Instants have no absolute meaning, so a decoded
+/// value is only the same point in time when decoded against the same
+/// reference, which in practice means within the encoding process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RelativeInstant {
+    pub instant: std::time::Instant,
+    pub reference: std::time::Instant,
+}
+
+impl RelativeInstant {
+    pub fn new(instant: std::time::Instant, reference: std::time::Instant) -> Self {
+        RelativeInstant { instant, reference }
+    }
+
+    /// The offset that is encoded, or `None` if `instant` is earlier than
+    /// `reference`.
+    pub fn elapsed(&self) -> Option<std::time::Duration> {
+        self.instant.checked_duration_since(self.reference)
+    }
+
+    /// Decodes an offset and applies it to `reference`.
+    pub fn from_bytes<R: std::io::Read>(
+        rd: &mut R,
+        reference: std::time::Instant,
+    ) -> ToBytesResult<Self> {
+        Self::from_value(read_value(rd)?, reference)
+    }
+
+    pub fn from_value(value: rmpv::Value, reference: std::time::Instant) -> ToBytesResult<Self> {
+        let items = Vec::<rmpv::Value>::try_from(value)?;
+        let [secs, nanos] = <[rmpv::Value; 2]>::try_from(items).map_err(|items| {
+            Error::InvalidTimestamp(format!("expected [secs, nanos], got {} items", items.len()))
+        })?;
+        let secs = u64::from_value(secs)?;
+        let nanos = u32::from_value(nanos)?;
+        if nanos >= 1_000_000_000 {
+            return Err(Error::InvalidTimestamp(format!(
+                "nanoseconds out of range: {}",
+                nanos
+            )));
+        }
+        let instant = reference
+            .checked_add(std::time::Duration::new(secs, nanos))
+            .ok_or_else(|| {
+                Error::InvalidTimestamp(format!("{}s {}ns is out of range", secs, nanos))
+            })?;
+        Ok(RelativeInstant { instant, reference })
+    }
+}
+
+/// Fails for instants earlier than their reference, which have no
+/// unsigned offset to write.
+impl ToBytes for RelativeInstant {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        let elapsed = self.elapsed().ok_or_else(|| {
+            Error::InvalidTimestamp("instant is earlier than its reference".to_string())
+        })?;
+        rmp::encode::write_array_len(wr, 2)?;
+        elapsed.as_secs().to_bytes(wr)?;
+        elapsed.subsec_nanos().to_bytes(wr)
+    }
+}
+
+/// Any msgpack scalar. Arrays, maps and ext values are rejected. `f32`
+/// values are widened to `Float(f64)`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scalar {
+    Nil,
+    Bool(bool),
+    Int(rmpv::Integer),
+    Float(f64),
+    Str(String),
+    Bin(Vec<u8>),
+}
+
+impl FromBytes for Scalar {
+    type Output = Scalar;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        match value {
+            rmpv::Value::Nil => Ok(Scalar::Nil),
+            rmpv::Value::Boolean(b) => Ok(Scalar::Bool(b)),
+            rmpv::Value::Integer(i) => Ok(Scalar::Int(i)),
+            rmpv::Value::F32(f) => Ok(Scalar::Float(f as f64)),
+            rmpv::Value::F64(f) => Ok(Scalar::Float(f)),
+            rmpv::Value::String(s) => Ok(Scalar::Str(String::try_from(rmpv::Value::String(s))?)),
+            rmpv::Value::Binary(b) => Ok(Scalar::Bin(b)),
+            other => Err(other.into()),
+        }
+    }
+}
+
+impl ToBytes for Scalar {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        let value = match self {
+            Scalar::Nil => rmpv::ValueRef::Nil,
+            Scalar::Bool(b) => rmpv::ValueRef::Boolean(*b),
+            Scalar::Int(i) => rmpv::ValueRef::Integer(*i),
+            Scalar::Float(f) => rmpv::ValueRef::F64(*f),
+            Scalar::Str(s) => rmpv::ValueRef::from(s.as_str()),
+            Scalar::Bin(b) => rmpv::ValueRef::Binary(b),
+        };
+        write_value_ref(wr, &value)?;
+        Ok(())
+    }
+}
+
+/// A single byte written as a 1-byte msgpack binary rather than an integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Byte(pub u8);
+
+impl FromBytes for Byte {
+    type Output = Byte;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        match value {
+            rmpv::Value::Binary(data) if data.len() == 1 => Ok(Byte(data[0])),
+            other => Err(other.into()),
+        }
+    }
+}
+
+impl ToBytes for Byte {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        rmp::encode::write_bin(wr, &[self.0])?;
+        Ok(())
+    }
+}
+
+/// Optional binary blob, written as msgpack binary when `Some` and nil when
+/// `None`. Plain `Vec<u8>` keeps encoding as an array of integers.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ByteVec(pub Option<Vec<u8>>);
+
+impl FromBytes for ByteVec {
+    type Output = ByteVec;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        match value {
+            rmpv::Value::Nil => Ok(ByteVec(None)),
+            other => Ok(ByteVec(Some(Vec::<u8>::try_from(other)?))),
+        }
+    }
+}
+
+impl ToBytes for ByteVec {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        match &self.0 {
+            Some(bytes) => write_value_ref(wr, &rmpv::ValueRef::Binary(bytes))?,
+            None => rmp::encode::write_nil(wr)?,
+        }
+        Ok(())
+    }
+}
+
+/// Boolean mask packed eight bits per byte. Written as msgpack binary: a
+/// big-endian `u32` bit count followed by the packed bytes, least
+/// significant bit first.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct BitVec(pub Vec<bool>);
+
+impl FromBytes for BitVec {
+    type Output = BitVec;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        let data = Vec::<u8>::try_from(value)?;
+        let (len, packed) = data
+            .split_first_chunk::<4>()
+            .ok_or(Error::TruncatedBitVec(data.len()))?;
+        let len = u32::from_be_bytes(*len) as usize;
+        if packed.len() != len.div_ceil(8) {
+            return Err(Error::BitVecLength {
+                bits: len,
+                expected: len.div_ceil(8),
+                found: packed.len(),
+            });
+        }
+        Ok(BitVec(
+            (0..len)
+                .map(|i| packed[i / 8] & (1 << (i % 8)) != 0)
+                .collect(),
+        ))
+    }
+}
+
+impl ToBytes for BitVec {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        let bits = &self.0;
+        let mut data = Vec::with_capacity(4 + bits.len().div_ceil(8));
+        data.extend_from_slice(&(bits.len() as u32).to_be_bytes());
+        for chunk in bits.chunks(8) {
+            data.push(
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0u8, |byte, (i, &bit)| byte | ((bit as u8) << i)),
+            );
+        }
+        write_value_ref(wr, &rmpv::ValueRef::Binary(&data))?;
+        Ok(())
+    }
+}
+
+/// A list that also decodes from a single non-array value, as a one element
+/// list. Encodes as a plain array.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OneOrMany<T>(pub Vec<T>);
+
+impl<T> FromBytes for OneOrMany<T>
+where
+    T: FromBytes<Output = T>,
+{
+    type Output = OneOrMany<T>;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Self::from_value_versioned(value, u32::MAX)
+    }
+
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
+        match value {
+            rmpv::Value::Array(_) => Ok(OneOrMany(Vec::<T>::from_value_versioned(value, version)?)),
+            other => Ok(OneOrMany(vec![T::from_value_versioned(other, version)?])),
+        }
+    }
+}
+
+impl<T: ToBytes> ToBytes for OneOrMany<T> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        self.0.to_bytes_versioned(wr, version)
+    }
+}
+
+/// A msgpack map decoded as an ordered list of pairs, keeping duplicate
+/// keys and key types that can't be hashed.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Pairs<K, V>(pub Vec<(K, V)>);
+
+impl<K, V> FromBytes for Pairs<K, V>
+where
+    K: FromBytes<Output = K>,
+    V: FromBytes<Output = V>,
+{
+    type Output = Pairs<K, V>;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Self::from_value_versioned(value, u32::MAX)
+    }
+
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
+        let values = Vec::<(rmpv::Value, rmpv::Value)>::try_from(value)?;
+
+        values
+            .into_iter()
+            .map(|(key, val)| {
+                Ok((
+                    K::from_value_versioned(key, version)?,
+                    V::from_value_versioned(val, version)?,
+                ))
+            })
+            .collect::<ToBytesResult<Vec<(K, V)>>>()
+            .map(Pairs)
+    }
+}
+
+impl<K: ToBytes, V: ToBytes> ToBytes for Pairs<K, V> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        let len = self.0.len() as u32;
+        rmp::encode::write_map_len(wr, len)?;
+        for (key, value) in &self.0 {
+            key.to_bytes_versioned(wr, version)?;
+            value.to_bytes_versioned(wr, version)?;
+        }
+        Ok(())
+    }
+}
+
+/// A `HashMap` that encodes its entries sorted by encoded key bytes, so the
+/// same contents always produce the same output.
+#[derive(Debug, Clone, Default)]
+pub struct SortedMap<K, V>(pub std::collections::HashMap<K, V>);
+
+impl<K, V> FromBytes for SortedMap<K, V>
+where
+    K: FromBytes<Output = K> + std::hash::Hash + Eq,
+    V: FromBytes<Output = V>,
+{
+    type Output = SortedMap<K, V>;
+
+    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+        Self::from_value_versioned(value, u32::MAX)
+    }
+
+    fn from_value_versioned(value: rmpv::Value, version: u32) -> ToBytesResult<Self::Output> {
+        std::collections::HashMap::<K, V>::from_value_versioned(value, version).map(SortedMap)
+    }
+}
+
+impl<K: ToBytes, V: ToBytes> ToBytes for SortedMap<K, V> {
+    fn to_bytes<W: Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+        self.to_bytes_versioned(wr, u32::MAX)
+    }
+
+    fn to_bytes_versioned<W: Write>(&self, wr: &mut W, version: u32) -> ToBytesResult<()> {
+        let mut entries = self
+            .0
+            .iter()
+            .map(|(key, value)| {
+                let mut key_bytes = Vec::new();
+                key.to_bytes_versioned(&mut key_bytes, version)?;
+                Ok((key_bytes, value))
+            })
+            .collect::<ToBytesResult<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let len = entries.len() as u32;
+        rmp::encode::write_map_len(wr, len)?;
+        for (key_bytes, value) in entries {
+            wr.write_all(&key_bytes)?;
+            value.to_bytes_versioned(wr, version)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    fn test_pairs_round_trip() {
+        let pairs = Pairs(vec![
+            (vec![1u8, 2], "a".to_string()),
+            (vec![3u8], "b".to_string()),
+            (vec![1u8, 2], "c".to_string()),
+        ]);
+        let mut buf = vec![];
+        pairs.to_bytes(&mut buf).unwrap();
+        let decoded = Pairs::<Vec<u8>, String>::from_bytes(&mut &buf[..]).unwrap();
+        assert_eq!(decoded, pairs);
+
+        let value = rmpv::decode::read_value(&mut &buf[..]).unwrap();
+        assert_eq!(value.as_map().unwrap().len(), 3);
+    }
+
+    #[rstest]
+    #[case(ByteVec(Some(vec![1, 2, 3])), rmpv::Value::Binary(vec![1, 2, 3]))]
+    #[case(ByteVec(Some(vec![])), rmpv::Value::Binary(vec![]))]
+    #[case(ByteVec(None), rmpv::Value::Nil)]
+    fn test_byte_vec_round_trip(#[case] input: ByteVec, #[case] expected: rmpv::Value) {
+        let mut buf = vec![];
+        input.to_bytes(&mut buf).unwrap();
+        assert_eq!(rmpv::decode::read_value(&mut &buf[..]).unwrap(), expected);
+        assert_eq!(ByteVec::from_bytes(&mut &buf[..]).unwrap(), input);
+    }
+
+    #[rstest]
+    #[case(vec![])]
+    #[case(vec![true])]
+    #[case(vec![true, false, true, true, false, false, true, false])]
+    #[case(vec![false, true, true, false, true, false, false, true, true, true])]
+    fn test_bit_vec_round_trip(#[case] bits: Vec<bool>) {
+        let input = BitVec(bits);
+        let mut buf = vec![];
+        input.to_bytes(&mut buf).unwrap();
+        let packed = Vec::<u8>::try_from(rmpv::decode::read_value(&mut &buf[..]).unwrap()).unwrap();
+        assert_eq!(packed.len(), 4 + input.0.len().div_ceil(8));
+        assert_eq!(BitVec::from_bytes(&mut &buf[..]).unwrap(), input);
+    }
+
+    #[rstest]
+    fn test_bit_vec_packing() {
+        let mut buf = vec![];
+        BitVec(vec![
+            true, false, false, false, false, false, false, false, false, true,
+        ])
+        .to_bytes(&mut buf)
+        .unwrap();
+        assert_eq!(buf, vec![0xc4, 6, 0, 0, 0, 10, 0x01, 0x02]);
+    }
+
+    #[rstest]
+    fn test_bit_vec_length_mismatch() {
+        let value = rmpv::Value::Binary(vec![0, 0, 0, 9, 0xff]);
+        assert!(matches!(
+            BitVec::from_value(value),
+            Err(Error::BitVecLength {
+                bits: 9,
+                expected: 2,
+                found: 1
+            })
+        ));
+
+        let value = rmpv::Value::Binary(vec![0, 0]);
+        assert!(matches!(
+            BitVec::from_value(value),
+            Err(Error::TruncatedBitVec(2))
+        ));
+    }
+
+    #[rstest]
+    #[case(0)]
+    #[case(42)]
+    #[case(255)]
+    fn test_byte_round_trip(#[case] input: u8) {
+        let mut buf = vec![];
+        Byte(input).to_bytes(&mut buf).unwrap();
+        assert_eq!(buf, vec![0xc4, 1, input]);
+        assert_eq!(Byte::from_bytes(&mut &buf[..]).unwrap(), Byte(input));
+    }
+
+    #[rstest]
+    #[case(rmpv::Value::Binary(vec![]))]
+    #[case(rmpv::Value::Binary(vec![1, 2]))]
+    #[case(rmpv::Value::from(1))]
+    fn test_byte_rejects_other_values(#[case] value: rmpv::Value) {
+        assert!(Byte::from_value(value).is_err());
+    }
+
+    #[rstest]
+    #[case(Scalar::Nil)]
+    #[case(Scalar::Bool(true))]
+    #[case(Scalar::Int(rmpv::Integer::from(-5)))]
+    #[case(Scalar::Int(rmpv::Integer::from(u64::MAX)))]
+    #[case(Scalar::Float(1.5))]
+    #[case(Scalar::Str("hi".to_string()))]
+    #[case(Scalar::Bin(vec![1, 2]))]
+    fn test_scalar_round_trip(#[case] input: Scalar) {
+        let mut buf = vec![];
+        input.to_bytes(&mut buf).unwrap();
+        assert_eq!(Scalar::from_bytes(&mut &buf[..]).unwrap(), input);
+    }
+
+    #[rstest]
+    #[case(rmpv::Value::Array(vec![]))]
+    #[case(rmpv::Value::Map(vec![]))]
+    #[case(rmpv::Value::Ext(1, vec![]))]
+    fn test_scalar_rejects_non_scalars(#[case] value: rmpv::Value) {
+        assert!(Scalar::from_value(value).is_err());
+    }
+
+    #[rstest]
+    #[case(rmpv::Value::from(7), vec![7])]
+    #[case(rmpv::Value::Array(vec![rmpv::Value::from(1), rmpv::Value::from(2)]), vec![1, 2])]
+    #[case(rmpv::Value::Array(vec![]), vec![])]
+    fn test_one_or_many(#[case] value: rmpv::Value, #[case] expected: Vec<u32>) {
+        assert_eq!(
+            OneOrMany::<u32>::from_value(value).unwrap(),
+            OneOrMany(expected)
+        );
+    }
+
+    #[rstest]
+    fn test_one_or_many_encodes_as_array() {
+        let mut buf = vec![];
+        OneOrMany(vec![5u8]).to_bytes(&mut buf).unwrap();
+        assert_eq!(buf, vec![0x91, 5]);
+        assert!(Vec::<u8>::from_bytes(&mut &[5u8][..]).is_err());
+    }
+
+    #[rstest]
+    fn test_sorted_map_round_trip() {
+        let map: std::collections::HashMap<String, u32> =
+            (0..20).map(|i| (format!("key{}", i), i)).collect();
+        let mut buf = Vec::new();
+        SortedMap(map.clone()).to_bytes(&mut buf).unwrap();
+
+        let decoded = SortedMap::<String, u32>::from_bytes(&mut &buf[..]).unwrap();
+        assert_eq!(decoded.0, map);
+    }
+
+    #[rstest]
+    fn test_relative_instant_round_trip() {
+        let reference = std::time::Instant::now();
+        let value = RelativeInstant::new(
+            reference + std::time::Duration::new(3, 250_000_000),
+            reference,
+        );
+        let mut buf = Vec::new();
+        value.to_bytes(&mut buf).unwrap();
+        assert_eq!(buf, vec![0x92, 0x03, 0xce, 0x0e, 0xe6, 0xb2, 0x80]);
+
+        let decoded = RelativeInstant::from_bytes(&mut &buf[..], reference).unwrap();
+        assert_eq!(decoded, value);
+
+        let other = reference + std::time::Duration::from_secs(60);
+        let rebased = RelativeInstant::from_bytes(&mut &buf[..], other).unwrap();
+        assert_eq!(rebased.elapsed(), value.elapsed());
+
+        let earlier = RelativeInstant::new(reference, other);
+        assert!(earlier.to_bytes(&mut Vec::new()).is_err());
+    }
+
+    #[rstest]
+    fn test_sorted_map_is_deterministic() {
+        let forward: std::collections::HashMap<u32, &str> = (0..50).map(|i| (i, "v")).collect();
+        let backward: std::collections::HashMap<u32, &str> =
+            (0..50).rev().map(|i| (i, "v")).collect();
+
+        let mut first = Vec::new();
+        SortedMap(forward).to_bytes(&mut first).unwrap();
+        let mut second = Vec::new();
+        SortedMap(backward).to_bytes(&mut second).unwrap();
+        assert_eq!(first, second);
+
+        let mut small = Vec::new();
+        SortedMap(std::collections::HashMap::from([(2u8, 0u8), (1, 0)]))
+            .to_bytes(&mut small)
+            .unwrap();
+        assert_eq!(small, vec![0x82, 0x01, 0x00, 0x02, 0x00]);
+    }
+}
@@ -1,29 +1,469 @@
 use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
 use quote::quote;
-use syn::{parse_macro_input, Data, DeriveInput, Fields};
+use syn::{parse_macro_input, parse_quote, Data, DataEnum, DeriveInput, Fields};
 
-#[proc_macro_derive(ToBytesDict)]
+#[derive(Default)]
+struct ContainerAttrs {
+    repr_int: bool,
+    /// Encode enum variants as a two element `[variant_name, payload]` array.
+    array_tag: bool,
+}
+
+fn parse_container_attrs(input: &DeriveInput) -> syn::Result<ContainerAttrs> {
+    let mut repr_int = None;
+    let mut array_tag = None;
+    for attr in &input.attrs {
+        if !attr.path().is_ident("tobytes") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("repr_int") {
+                repr_int = Some(meta.path.clone());
+                Ok(())
+            } else if meta.path.is_ident("tag") {
+                let tag: syn::LitStr = meta.value()?.parse()?;
+                if tag.value() != "array" {
+                    return Err(syn::Error::new_spanned(tag, "unsupported tobytes tag, expected \"array\""));
+                }
+                array_tag = Some(meta.path.clone());
+                Ok(())
+            } else {
+                Err(meta.error("unsupported tobytes attribute"))
+            }
+        })?;
+    }
+
+    if !matches!(input.data, Data::Enum(_)) {
+        if let Some(path) = &repr_int {
+            return Err(syn::Error::new_spanned(path, "tobytes `repr_int` is only supported on enums"));
+        }
+        if let Some(path) = &array_tag {
+            return Err(syn::Error::new_spanned(path, "tobytes `tag` is only supported on enums"));
+        }
+    }
+    if let (Some(_), Some(path)) = (&repr_int, &array_tag) {
+        return Err(syn::Error::new_spanned(path, "tobytes `repr_int` and `tag` cannot be used together"));
+    }
+    Ok(ContainerAttrs {
+        repr_int: repr_int.is_some(),
+        array_tag: array_tag.is_some(),
+    })
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    /// Module providing `to_bytes`/`from_value` functions used in place of
+    /// the field type's own impls.
+    with: Option<syn::Path>,
+    /// Schema version the field was introduced in. It is only written at or
+    /// above this version, and defaults when absent on decode.
+    since: Option<u32>,
+}
+
+fn parse_field_attrs(field: &syn::Field) -> syn::Result<FieldAttrs> {
+    let mut attrs = FieldAttrs::default();
+    for attr in &field.attrs {
+        if !attr.path().is_ident("tobytes") {
+            continue;
+        }
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("with") {
+                let module: syn::LitStr = meta.value()?.parse()?;
+                attrs.with = Some(module.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("since") {
+                let version: syn::LitInt = meta.value()?.parse()?;
+                attrs.since = Some(version.base10_parse()?);
+                Ok(())
+            } else {
+                Err(meta.error("unsupported tobytes field attribute"))
+            }
+        })?;
+    }
+    Ok(attrs)
+}
+
+/// Generates the encoding of a single field value, passing the schema
+/// version down to nested values. `tobytes(since)` is only accepted where
+/// `allow_since` is set, i.e. on named struct fields.
+fn encode_field(
+    field: &syn::Field,
+    value: TokenStream2,
+    allow_since: bool,
+) -> syn::Result<TokenStream2> {
+    let attrs = parse_field_attrs(field)?;
+    if attrs.since.is_some() && !allow_since {
+        return Err(syn::Error::new_spanned(
+            field,
+            "tobytes(since) is only supported on named fields",
+        ));
+    }
+    Ok(match attrs.with {
+        Some(module) => quote! { #module::to_bytes(&#value, wr)?; },
+        None => quote! { #value.to_bytes_versioned(wr, tobytes_version)?; },
+    })
+}
+
+fn decode_field(field: &syn::Field, value: TokenStream2) -> syn::Result<TokenStream2> {
+    let attrs = parse_field_attrs(field)?;
+    let field_type = &field.ty;
+    Ok(match attrs.with {
+        Some(module) => quote! { #module::from_value(#value)? },
+        None => quote! { <#field_type>::from_value_versioned(#value, tobytes_version)? },
+    })
+}
+
+/// Whether a field type is written as `Option<...>` (by its last path
+/// segment, so `std::option::Option` matches too).
+fn is_option(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+/// Renders a field type as source-like text, e.g. `Vec<u8>`.
+fn type_name(ty: &syn::Type) -> String {
+    let mut name = quote!(#ty).to_string();
+    let replacements = [
+        (" < ", "<"),
+        (" <", "<"),
+        (" >", ">"),
+        (" , ", ", "),
+        (" :: ", "::"),
+        (":: ", "::"),
+        ("& ", "&"),
+        (" ;", ";"),
+    ];
+    for (spaced, tight) in replacements {
+        name = name.replace(spaced, tight);
+    }
+    name
+}
+
+/// Generates `tobytes_schema()` for a struct, listing its field names (or
+/// indices, for tuple structs) and field types.
+fn struct_schema(input: &DeriveInput, fields: &Fields) -> TokenStream2 {
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let schema_fields = fields.iter().enumerate().map(|(i, f)| {
+        let field_name = f.ident.as_ref().map_or_else(|| i.to_string(), |ident| ident.to_string());
+        let field_type = type_name(&f.ty);
+        quote! {
+            ::tobytes::SchemaField {
+                name: #field_name,
+                type_name: #field_type,
+            }
+        }
+    });
+
+    quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            pub fn tobytes_schema() -> ::tobytes::Schema {
+                ::tobytes::Schema {
+                    name: #name_str,
+                    fields: vec![#(#schema_fields),*],
+                }
+            }
+        }
+    }
+}
+
+/// Returns the input's generics with `bound` added for every type parameter,
+/// so generic fields can be encoded and decoded.
+fn bounded_generics(input: &DeriveInput, bound: impl Fn(&syn::Ident) -> syn::WherePredicate) -> syn::Generics {
+    let mut generics = input.generics.clone();
+    let where_clause = generics.make_where_clause();
+    for param in input.generics.type_params() {
+        where_clause.predicates.push(bound(&param.ident));
+    }
+    generics
+}
+
+/// Checks that every variant of a `repr_int` enum is fieldless, so it can be
+/// cast to its discriminant.
+fn check_repr_int_enum(data: &DataEnum) -> syn::Result<()> {
+    for variant in &data.variants {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                variant,
+                "tobytes(repr_int) enums must only have fieldless variants",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Generates the body of `to_bytes` for an enum encoded as
+/// `[variant_name, payload]`. Unit variants have a nil payload, newtype
+/// variants their single value, tuple variants an array and struct variants
+/// a map.
+fn array_tag_enum_encoding(data: &DataEnum) -> syn::Result<TokenStream2> {
+    let arms = data.variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let tag = ident.to_string();
+        Ok(match &variant.fields {
+            Fields::Unit => quote! {
+                Self::#ident => {
+                    #tag.to_bytes(wr)?;
+                    rmp::encode::write_nil(wr)?;
+                }
+            },
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let encoding = encode_field(&fields.unnamed[0], quote! { (*field_0) }, false)?;
+                quote! {
+                    Self::#ident(field_0) => {
+                        #tag.to_bytes(wr)?;
+                        #encoding
+                    }
+                }
+            }
+            Fields::Unnamed(fields) => {
+                let field_count = fields.unnamed.len();
+                let vars: Vec<_> = (0..field_count)
+                    .map(|i| quote::format_ident!("field_{}", i))
+                    .collect();
+                let encodings = fields
+                    .unnamed
+                    .iter()
+                    .zip(&vars)
+                    .map(|(f, var)| encode_field(f, quote! { (*#var) }, false))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                quote! {
+                    Self::#ident(#(#vars),*) => {
+                        #tag.to_bytes(wr)?;
+                        rmp::encode::write_array_len(wr, #field_count as u32)?;
+                        #(#encodings)*
+                    }
+                }
+            }
+            Fields::Named(fields) => {
+                let field_count = fields.named.len();
+                let field_names: Vec<_> = fields.named.iter().map(|f| &f.ident).collect();
+                let encodings = fields
+                    .named
+                    .iter()
+                    .map(|f| {
+                        let field_name = &f.ident;
+                        let field_name_str = field_name.as_ref().unwrap().to_string();
+                        let encoding = encode_field(f, quote! { (*#field_name) }, false)?;
+                        Ok(quote! {
+                            #field_name_str.to_bytes(wr)?;
+                            #encoding
+                        })
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+                quote! {
+                    Self::#ident { #(#field_names),* } => {
+                        #tag.to_bytes(wr)?;
+                        rmp::encode::write_map_len(wr, #field_count as u32)?;
+                        #(#encodings)*
+                    }
+                }
+            }
+        })
+    });
+    let arms = arms.collect::<syn::Result<Vec<_>>>()?;
+    Ok(quote! {
+        rmp::encode::write_array_len(wr, 2)?;
+        match self {
+            #(#arms)*
+        }
+        Ok(())
+    })
+}
+
+/// Generates the body of `from_value` for an enum encoded as
+/// `[variant_name, payload]`, the inverse of `array_tag_enum_encoding`.
+fn array_tag_enum_decoding(name: &syn::Ident, data: &DataEnum) -> syn::Result<TokenStream2> {
+    let name_str = name.to_string();
+    let arms = data.variants.iter().map(|variant| {
+        let ident = &variant.ident;
+        let tag = ident.to_string();
+        Ok(match &variant.fields {
+            Fields::Unit => quote! {
+                #tag => {
+                    if !payload.is_nil() {
+                        use std::io;
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!("Expected nil payload for {}::{}", #name_str, #tag)
+                        ).into());
+                    }
+                    Ok(Self::#ident)
+                }
+            },
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                let decoding = decode_field(&fields.unnamed[0], quote! { payload })?;
+                quote! {
+                    #tag => Ok(Self::#ident(#decoding)),
+                }
+            }
+            Fields::Unnamed(fields) => {
+                let field_count = fields.unnamed.len();
+                let decodings = fields
+                    .unnamed
+                    .iter()
+                    .map(|f| {
+                        let decoding = decode_field(f, quote! { val })?;
+                        Ok(quote! {
+                            {
+                                let val = items.next().unwrap();
+                                #decoding
+                            }
+                        })
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+                quote! {
+                    #tag => {
+                        let items = Vec::<rmpv::Value>::try_from(payload)?;
+                        if items.len() != #field_count {
+                            use std::io;
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!("Expected {} fields, got {}", #field_count, items.len())
+                            ).into());
+                        }
+                        let mut items = items.into_iter();
+                        Ok(Self::#ident(#(#decodings),*))
+                    }
+                }
+            }
+            Fields::Named(fields) => {
+                let field_names: Vec<_> = fields.named.iter().map(|f| &f.ident).collect();
+                let decodings = fields
+                    .named
+                    .iter()
+                    .map(|f| {
+                        let field_name = &f.ident;
+                        let field_name_str = field_name.as_ref().unwrap().to_string();
+                        let decoding = decode_field(f, quote! { val })?;
+                        Ok(quote! {
+                            let #field_name = {
+                                let val = map.remove(#field_name_str)
+                                    .ok_or_else(|| {
+                                        use std::io;
+                                        io::Error::new(
+                                            io::ErrorKind::InvalidData,
+                                            format!("Missing field: {}", #field_name_str)
+                                        )
+                                    })?;
+                                #decoding
+                            };
+                        })
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+                quote! {
+                    #tag => {
+                        let pairs = match payload {
+                            rmpv::Value::Map(pairs) => pairs,
+                            other => return Err(::tobytes::ToBytesError::expected_map(&other)),
+                        };
+                        let mut map = std::collections::HashMap::new();
+                        for (key, val) in pairs {
+                            let key_str = match key {
+                                rmpv::Value::String(key) => String::try_from(rmpv::Value::String(key))?,
+                                other => return Err(::tobytes::ToBytesError::NonStringMapKey(other)),
+                            };
+                            map.insert(key_str, val);
+                        }
+                        #(#decodings)*
+                        Ok(Self::#ident { #(#field_names),* })
+                    }
+                }
+            }
+        })
+    });
+    let arms = arms.collect::<syn::Result<Vec<_>>>()?;
+    Ok(quote! {
+        let items = Vec::<rmpv::Value>::try_from(value)?;
+        let [tag, payload]: [rmpv::Value; 2] = items.try_into().map_err(|items: Vec<rmpv::Value>| {
+            use std::io;
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Expected [tag, payload] array for {}, got {} elements", #name_str, items.len())
+            )
+        })?;
+        let tag = String::try_from(tag)?;
+        match tag.as_str() {
+            #(#arms)*
+            other => {
+                use std::io;
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unknown variant {} for {}", other, #name_str)
+                ).into())
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(ToBytesDict, attributes(tobytes))]
 pub fn derive_to_bytes_dict(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
+    let generics = bounded_generics(&input, |param| parse_quote!(#param: ::tobytes::ToBytes));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let attrs = match parse_container_attrs(&input) {
+        Ok(attrs) => attrs,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
     let expanded = match &input.data {
         Data::Struct(data) => match &data.fields {
             Fields::Named(fields) => {
-                let field_count = fields.named.len();
-                let field_encodings = fields.named.iter().map(|f| {
+                let field_attrs = match fields.named.iter().map(parse_field_attrs).collect::<syn::Result<Vec<_>>>() {
+                    Ok(attrs) => attrs,
+                    Err(err) => return err.to_compile_error().into(),
+                };
+                let base_count = field_attrs.iter().filter(|a| a.since.is_none()).count();
+                let versioned_counts = field_attrs.iter().filter_map(|a| a.since).map(|since| {
+                    quote! {
+                        if tobytes_version >= #since {
+                            field_count += 1;
+                        }
+                    }
+                });
+                let field_encodings = fields.named.iter().zip(&field_attrs).map(|(f, attrs)| {
                     let field_name = &f.ident;
                     let field_name_str = field_name.as_ref().unwrap().to_string();
-                    quote! {
+                    let value_encoding = encode_field(f, quote! { self.#field_name }, true)?;
+                    let encoding = quote! {
                         #field_name_str.to_bytes(wr)?;
-                        self.#field_name.to_bytes(wr)?;
-                    }
+                        #value_encoding
+                    };
+                    Ok(match attrs.since {
+                        Some(since) => quote! {
+                            if tobytes_version >= #since {
+                                #encoding
+                            }
+                        },
+                        None => encoding,
+                    })
                 });
+                let field_encodings = match field_encodings.collect::<syn::Result<Vec<_>>>() {
+                    Ok(encodings) => encodings,
+                    Err(err) => return err.to_compile_error().into(),
+                };
 
                 quote! {
-                    impl ToBytes for #name {
+                    impl #impl_generics ToBytes for #name #ty_generics #where_clause {
                         fn to_bytes<W: std::io::Write>(&self, wr: &mut W) -> ToBytesResult<()> {
-                            rmp::encode::write_map_len(wr, #field_count as u32)?;
+                            self.to_bytes_versioned(wr, u32::MAX)
+                        }
+
+                        #[allow(unused_variables)]
+                        fn to_bytes_versioned<W: std::io::Write>(&self, wr: &mut W, tobytes_version: u32) -> ToBytesResult<()> {
+                            #[allow(unused_mut)]
+                            let mut field_count: usize = #base_count;
+                            #(#versioned_counts)*
+                            rmp::encode::write_map_len(wr, field_count as u32)?;
                             #(#field_encodings)*
                             Ok(())
                         }
@@ -32,16 +472,23 @@ pub fn derive_to_bytes_dict(input: TokenStream) -> TokenStream {
             }
             Fields::Unnamed(fields) => {
                 let field_count = fields.unnamed.len();
-                let field_encodings = (0..field_count).map(|i| {
+                let field_encodings = fields.unnamed.iter().enumerate().map(|(i, f)| {
                     let index = syn::Index::from(i);
-                    quote! {
-                        self.#index.to_bytes(wr)?;
-                    }
+                    encode_field(f, quote! { self.#index }, false)
                 });
+                let field_encodings = match field_encodings.collect::<syn::Result<Vec<_>>>() {
+                    Ok(encodings) => encodings,
+                    Err(err) => return err.to_compile_error().into(),
+                };
 
                 quote! {
-                    impl ToBytes for #name {
+                    impl #impl_generics ToBytes for #name #ty_generics #where_clause {
                         fn to_bytes<W: std::io::Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+                            self.to_bytes_versioned(wr, u32::MAX)
+                        }
+
+                        #[allow(unused_variables)]
+                        fn to_bytes_versioned<W: std::io::Write>(&self, wr: &mut W, tobytes_version: u32) -> ToBytesResult<()> {
                             rmp::encode::write_array_len(wr, #field_count as u32)?;
                             #(#field_encodings)*
                             Ok(())
@@ -51,7 +498,7 @@ pub fn derive_to_bytes_dict(input: TokenStream) -> TokenStream {
             }
             Fields::Unit => {
                 quote! {
-                    impl ToBytes for #name {
+                    impl #impl_generics ToBytes for #name #ty_generics #where_clause {
                         fn to_bytes<W: std::io::Write>(&self, wr: &mut W) -> ToBytesResult<()> {
                             rmp::encode::write_array_len(wr, 0)?;
                             Ok(())
@@ -60,6 +507,42 @@ pub fn derive_to_bytes_dict(input: TokenStream) -> TokenStream {
                 }
             }
         },
+        Data::Enum(data) if attrs.repr_int => {
+            if let Err(err) = check_repr_int_enum(data) {
+                return err.to_compile_error().into();
+            }
+            let variants = data.variants.iter().map(|v| &v.ident);
+
+            quote! {
+                impl #impl_generics ToBytes for #name #ty_generics #where_clause {
+                    fn to_bytes<W: std::io::Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+                        let discriminant: i64 = match self {
+                            #(Self::#variants => Self::#variants as i64,)*
+                        };
+                        discriminant.to_bytes(wr)
+                    }
+                }
+            }
+        }
+        Data::Enum(data) if attrs.array_tag => {
+            let encoding = match array_tag_enum_encoding(data) {
+                Ok(encoding) => encoding,
+                Err(err) => return err.to_compile_error().into(),
+            };
+
+            quote! {
+                impl #impl_generics ToBytes for #name #ty_generics #where_clause {
+                    fn to_bytes<W: std::io::Write>(&self, wr: &mut W) -> ToBytesResult<()> {
+                        self.to_bytes_versioned(wr, u32::MAX)
+                    }
+
+                    #[allow(unused_variables)]
+                    fn to_bytes_versioned<W: std::io::Write>(&self, wr: &mut W, tobytes_version: u32) -> ToBytesResult<()> {
+                        #encoding
+                    }
+                }
+            }
+        }
         Data::Enum(_) => {
             return syn::Error::new_spanned(
                 &input,
@@ -78,13 +561,28 @@ pub fn derive_to_bytes_dict(input: TokenStream) -> TokenStream {
         }
     };
 
-    TokenStream::from(expanded)
+    let schema = match &input.data {
+        Data::Struct(data) => struct_schema(&input, &data.fields),
+        _ => quote! {},
+    };
+
+    TokenStream::from(quote! {
+        #expanded
+        #schema
+    })
 }
 
-#[proc_macro_derive(FromBytesDict)]
+#[proc_macro_derive(FromBytesDict, attributes(tobytes))]
 pub fn derive_from_bytes_dict(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
+    let generics =
+        bounded_generics(&input, |param| parse_quote!(#param: ::tobytes::FromBytes<Output = #param>));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let attrs = match parse_container_attrs(&input) {
+        Ok(attrs) => attrs,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
     let expanded = match &input.data {
         Data::Struct(data) => match &data.fields {
@@ -92,8 +590,24 @@ pub fn derive_from_bytes_dict(input: TokenStream) -> TokenStream {
                 let field_decodings = fields.named.iter().map(|f| {
                     let field_name = &f.ident;
                     let field_name_str = field_name.as_ref().unwrap().to_string();
-                    let field_type = &f.ty;
-                    quote! {
+                    let value_decoding = decode_field(f, quote! { val })?;
+                    if let Some(since) = parse_field_attrs(f)?.since {
+                        return Ok(quote! {
+                            let #field_name = match map.remove(#field_name_str) {
+                                Some(val) if tobytes_version >= #since => #value_decoding,
+                                _ => Default::default(),
+                            };
+                        });
+                    }
+                    if is_option(&f.ty) {
+                        return Ok(quote! {
+                            let #field_name = match map.remove(#field_name_str) {
+                                Some(val) => #value_decoding,
+                                None => None,
+                            };
+                        });
+                    }
+                    Ok(quote! {
                         let #field_name = {
                             let val = map.remove(#field_name_str)
                                 .ok_or_else(|| {
@@ -103,24 +617,54 @@ pub fn derive_from_bytes_dict(input: TokenStream) -> TokenStream {
                                         format!("Missing field: {}", #field_name_str)
                                     )
                                 })?;
-                            <#field_type>::from_value(val)?
+                            #value_decoding
                         };
-                    }
+                    })
                 });
+                let field_decodings = match field_decodings.collect::<syn::Result<Vec<_>>>() {
+                    Ok(decodings) => decodings,
+                    Err(err) => return err.to_compile_error().into(),
+                };
 
                 let field_names = fields.named.iter().map(|f| &f.ident);
+                let field_name_strs = fields.named.iter().map(|f| f.ident.as_ref().unwrap().to_string());
+                let field_count = fields.named.len();
 
                 quote! {
-                    impl FromBytes for #name {
+                    impl #impl_generics FromBytes for #name #ty_generics #where_clause {
                         type Output = Self;
 
                         fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
-                            let pairs = Vec::<(rmpv::Value, rmpv::Value)>::try_from(value)?;
-                            let mut map = std::collections::HashMap::new();
+                            Self::from_value_versioned(value, u32::MAX)
+                        }
 
-                            for (key, val) in pairs {
-                                let key_str = String::try_from(key)?;
-                                map.insert(key_str, val);
+                        #[allow(unused_variables)]
+                        fn from_value_versioned(value: rmpv::Value, tobytes_version: u32) -> ToBytesResult<Self::Output> {
+                            let mut map = std::collections::HashMap::new();
+                            match value {
+                                rmpv::Value::Map(pairs) => {
+                                    for (key, val) in pairs {
+                                        let key_str = match key {
+                                            rmpv::Value::String(key) => String::try_from(rmpv::Value::String(key))?,
+                                            other => return Err(::tobytes::ToBytesError::NonStringMapKey(other)),
+                                        };
+                                        map.insert(key_str, val);
+                                    }
+                                }
+                                // Positional form: items follow field declaration order.
+                                rmpv::Value::Array(items) => {
+                                    if items.len() > #field_count {
+                                        use std::io;
+                                        return Err(io::Error::new(
+                                            io::ErrorKind::InvalidData,
+                                            format!("Expected at most {} fields, got {}", #field_count, items.len())
+                                        ).into());
+                                    }
+                                    for (key, val) in [#(#field_name_strs),*].into_iter().zip(items) {
+                                        map.insert(key.to_string(), val);
+                                    }
+                                }
+                                other => return Err(::tobytes::ToBytesError::expected_map(&other)),
                             }
 
                             #(#field_decodings)*
@@ -135,9 +679,9 @@ pub fn derive_from_bytes_dict(input: TokenStream) -> TokenStream {
             Fields::Unnamed(fields) => {
                 let field_count = fields.unnamed.len();
                 let field_decodings = fields.unnamed.iter().enumerate().map(|(i, f)| {
-                    let field_type = &f.ty;
                     let var_name = quote::format_ident!("field_{}", i);
-                    quote! {
+                    let value_decoding = decode_field(f, quote! { val })?;
+                    Ok(quote! {
                         let #var_name = {
                             let val = items.get(#i)
                                 .ok_or_else(|| {
@@ -148,21 +692,37 @@ pub fn derive_from_bytes_dict(input: TokenStream) -> TokenStream {
                                     )
                                 })?
                                 .clone();
-                            <#field_type>::from_value(val)?
+                            #value_decoding
                         };
-                    }
+                    })
                 });
+                let field_decodings = match field_decodings.collect::<syn::Result<Vec<_>>>() {
+                    Ok(decodings) => decodings,
+                    Err(err) => return err.to_compile_error().into(),
+                };
 
                 let field_vars = (0..field_count).map(|i| {
                     quote::format_ident!("field_{}", i)
                 });
 
                 quote! {
-                    impl FromBytes for #name {
+                    impl #impl_generics FromBytes for #name #ty_generics #where_clause {
                         type Output = Self;
 
                         fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+                            Self::from_value_versioned(value, u32::MAX)
+                        }
+
+                        #[allow(unused_variables)]
+                        fn from_value_versioned(value: rmpv::Value, tobytes_version: u32) -> ToBytesResult<Self::Output> {
                             let items = Vec::<rmpv::Value>::try_from(value)?;
+                            if items.len() != #field_count {
+                                use std::io;
+                                return Err(io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    format!("Expected {} fields, got {}", #field_count, items.len())
+                                ).into());
+                            }
 
                             #(#field_decodings)*
 
@@ -173,7 +733,7 @@ pub fn derive_from_bytes_dict(input: TokenStream) -> TokenStream {
             }
             Fields::Unit => {
                 quote! {
-                    impl FromBytes for #name {
+                    impl #impl_generics FromBytes for #name #ty_generics #where_clause {
                         type Output = Self;
 
                         fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
@@ -191,6 +751,54 @@ pub fn derive_from_bytes_dict(input: TokenStream) -> TokenStream {
                 }
             }
         },
+        Data::Enum(data) if attrs.repr_int => {
+            if let Err(err) = check_repr_int_enum(data) {
+                return err.to_compile_error().into();
+            }
+            let variants = data.variants.iter().map(|v| &v.ident);
+            let name_str = name.to_string();
+
+            quote! {
+                impl #impl_generics FromBytes for #name #ty_generics #where_clause {
+                    type Output = Self;
+
+                    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+                        let discriminant = i64::from_value(value)?;
+                        match discriminant {
+                            #(d if d == Self::#variants as i64 => Ok(Self::#variants),)*
+                            d => {
+                                use std::io;
+                                Err(io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    format!("Unknown discriminant {} for {}", d, #name_str)
+                                ).into())
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        Data::Enum(data) if attrs.array_tag => {
+            let decoding = match array_tag_enum_decoding(name, data) {
+                Ok(decoding) => decoding,
+                Err(err) => return err.to_compile_error().into(),
+            };
+
+            quote! {
+                impl #impl_generics FromBytes for #name #ty_generics #where_clause {
+                    type Output = Self;
+
+                    fn from_value(value: rmpv::Value) -> ToBytesResult<Self::Output> {
+                        Self::from_value_versioned(value, u32::MAX)
+                    }
+
+                    #[allow(unused_variables)]
+                    fn from_value_versioned(value: rmpv::Value, tobytes_version: u32) -> ToBytesResult<Self::Output> {
+                        #decoding
+                    }
+                }
+            }
+        }
         Data::Enum(_) => {
             return syn::Error::new_spanned(
                 &input,